@@ -0,0 +1,225 @@
+// A UGI/UAI engine that does what its script says, for testing the match runner without real engines
+//
+// fake_engine [SCRIPT] [--game GAME]
+//
+// The script is a file with one rule per line, # starts a comment. Moves are counted from 1 by the
+// number of go commands this engine has been sent in the game.
+//
+// moves a1 b2      Play these moves in turn, then keep playing the last one (default 0000)
+// ponder b1 c2     Expect these replies to the moves, counted the same way (default none)
+// gameover 6       The game is over once 6 plies have been played (default never)
+// result p1win     The result given when asked (default draw)
+// crash 3          Write to stderr and exit instead of playing move 3
+// stall 3          Stop answering at move 3
+// sleep 3 200      Take 200ms over move 3
+// readydelay 300   Wait 300ms before every readyok
+// noturn           Exit when asked whose turn it is
+// noise            Write malformed lines before every reply
+//
+// Being queried while pondering is an error, the engine writes to stderr and exits.
+//
+// Run it with prefix=/path/to/fake_engine and path=/path/to/script, or give the script a
+// #!/path/to/fake_engine line and make it executable.
+use std::{
+    env,
+    fs::read_to_string,
+    io::{BufRead, Write},
+    process::exit,
+    thread,
+    time::Duration,
+};
+
+#[derive(Default)]
+struct Script {
+    moves: Vec<String>,
+    ponders: Vec<String>,
+    gameover: Option<usize>,
+    result: Option<String>,
+    crash: Option<usize>,
+    stall: Option<usize>,
+    sleeps: Vec<(usize, u64)>,
+    ready_delay: u64,
+    no_turn: bool,
+    noise: bool,
+}
+
+fn parse_script(text: &str) -> Result<Script, String> {
+    let mut script = Script::default();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let number = |idx: usize| -> Result<usize, String> {
+            words
+                .get(idx)
+                .and_then(|word| word.parse().ok())
+                .ok_or(format!("Expected a number in \"{}\"", line.trim()))
+        };
+        match words.first() {
+            None => {}
+            Some(&"moves") => script.moves = words[1..].iter().map(|mv| mv.to_string()).collect(),
+            Some(&"ponder") => {
+                script.ponders = words[1..].iter().map(|mv| mv.to_string()).collect()
+            }
+            Some(&"gameover") => script.gameover = Some(number(1)?),
+            Some(&"result") => script.result = words.get(1).map(|result| result.to_string()),
+            Some(&"crash") => script.crash = Some(number(1)?),
+            Some(&"stall") => script.stall = Some(number(1)?),
+            Some(&"sleep") => script.sleeps.push((number(1)?, number(2)? as u64)),
+            Some(&"readydelay") => script.ready_delay = number(1)? as u64,
+            Some(&"noturn") => script.no_turn = true,
+            Some(&"noise") => script.noise = true,
+            Some(word) => return Err(format!("Unknown rule \"{}\"", word)),
+        }
+    }
+    Ok(script)
+}
+
+struct Engine {
+    script: Script,
+    // Plies played since the position was set
+    ply: usize,
+    is_p1_first: bool,
+    num_moves: usize,
+    is_pondering: bool,
+}
+
+impl Engine {
+    fn reply(&self, msg: &str) {
+        let mut stdout = std::io::stdout().lock();
+        if self.script.noise {
+            for line in [
+                "",
+                "info",
+                "info time",
+                "info depth 3 time soon",
+                "info string bestmove z9",
+                "response maybe",
+                "best move a1",
+            ] {
+                let _ = writeln!(stdout, "{}", line);
+            }
+        }
+        let _ = writeln!(stdout, "{}", msg);
+        let _ = stdout.flush();
+    }
+
+    fn set_position(&mut self, words: &[&str]) {
+        self.ply = 0;
+        self.is_p1_first = match words.first() {
+            Some(&"fen") => words.get(2) != Some(&"o"),
+            _ => true,
+        };
+        if let Some(idx) = words.iter().position(|word| *word == "moves") {
+            self.ply += words.len() - idx - 1;
+        }
+    }
+
+    fn bestmove(&mut self) {
+        self.num_moves += 1;
+        let num = self.num_moves;
+
+        if self.script.crash == Some(num) {
+            eprintln!("Crashing at move {}", num);
+            exit(1);
+        }
+        if self.script.stall == Some(num) {
+            loop {
+                thread::sleep(Duration::from_secs(60));
+            }
+        }
+        for (_, ms) in self.script.sleeps.iter().filter(|(at, _)| *at == num) {
+            thread::sleep(Duration::from_millis(*ms));
+        }
+
+        let mv = self
+            .script
+            .moves
+            .get(num - 1)
+            .or(self.script.moves.last())
+            .map_or("0000", |mv| mv.as_str());
+        match self
+            .script
+            .ponders
+            .get(num - 1)
+            .or(self.script.ponders.last())
+        {
+            Some(ponder) => self.reply(&format!("bestmove {} ponder {}", mv, ponder)),
+            None => self.reply(&format!("bestmove {}", mv)),
+        }
+    }
+
+    fn query(&self, words: &[&str]) {
+        if self.is_pondering {
+            eprintln!("Queried while pondering");
+            exit(1);
+        }
+        let response = match words.first() {
+            Some(&"gameover") => {
+                let is_over = self.script.gameover.is_some_and(|ply| self.ply >= ply);
+                is_over.to_string()
+            }
+            Some(&"p1turn") => {
+                if self.script.no_turn {
+                    exit(1);
+                }
+                (self.is_p1_first == self.ply.is_multiple_of(2)).to_string()
+            }
+            Some(&"result") => self.script.result.clone().unwrap_or("draw".to_string()),
+            _ => return,
+        };
+        self.reply(&format!("response {}", response));
+    }
+}
+
+fn main() {
+    let script = match env::args().nth(1).filter(|arg| !arg.starts_with("--")) {
+        Some(path) => read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))
+            .and_then(|text| parse_script(&text)),
+        None => Ok(Script::default()),
+    };
+    let script = script.unwrap_or_else(|msg| {
+        eprintln!("{}", msg);
+        exit(2);
+    });
+
+    let mut engine = Engine {
+        script,
+        ply: 0,
+        is_p1_first: true,
+        num_moves: 0,
+        is_pondering: false,
+    };
+
+    for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.first() {
+            Some(&"ugi") => {
+                engine.reply("id name FakeEngine");
+                engine.reply("ugiok");
+            }
+            Some(&"uai") => {
+                engine.reply("id name FakeEngine");
+                engine.reply("uaiok");
+            }
+            Some(&"isready") => {
+                thread::sleep(Duration::from_millis(engine.script.ready_delay));
+                engine.reply("readyok");
+            }
+            Some(&"position") => engine.set_position(&words[1..]),
+            Some(&"moves") => engine.ply += words.len() - 1,
+            Some(&"go") if words.get(1) == Some(&"ponder") => engine.is_pondering = true,
+            Some(&"go") | Some(&"ponderhit") => {
+                engine.is_pondering = false;
+                engine.bestmove();
+            }
+            Some(&"stop") if engine.is_pondering => {
+                engine.is_pondering = false;
+                engine.reply("bestmove 0000");
+            }
+            Some(&"query") => engine.query(&words[1..]),
+            Some(&"quit") => break,
+            _ => {}
+        }
+    }
+}
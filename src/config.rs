@@ -0,0 +1,625 @@
+use crate::{
+    parse::{ParseError, read_spec},
+    players::clock::parse_tc,
+    tournament::{
+        PlayerSettings, QueueSettings, QueueTest, SPRTSettings, Schedule, TournamentSettings,
+        TuneSettings, parse_affinity,
+    },
+};
+use serde::{Deserialize, de::DeserializeOwned};
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+struct EngineConfig {
+    name: Option<String>,
+    path: Option<String>,
+    proto: Option<String>,
+    parameters: Option<String>,
+    prefix: Option<String>,
+    tc: Option<String>,
+    movetime: Option<usize>,
+    depth: Option<usize>,
+    nodes: Option<usize>,
+    ponder: Option<bool>,
+    debug: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct PlayerConfig {
+    engine: Option<String>,
+    name: Option<String>,
+    path: Option<String>,
+    proto: Option<String>,
+    parameters: Option<String>,
+    prefix: Option<String>,
+    tc: Option<String>,
+    movetime: Option<usize>,
+    depth: Option<usize>,
+    nodes: Option<usize>,
+    ponder: Option<bool>,
+    debug: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TuneConfig {
+    spec: String,
+    output: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    engines: HashMap<String, EngineConfig>,
+    #[serde(default)]
+    players: Vec<PlayerConfig>,
+    game: Option<String>,
+    openings: Option<String>,
+    games: Option<usize>,
+    threads: Option<usize>,
+    timemargin: Option<u64>,
+    stalltime: Option<u64>,
+    enginetime: Option<bool>,
+    affinity: Option<String>,
+    updates: Option<usize>,
+    pgn: Option<String>,
+    results_json: Option<String>,
+    results_csv: Option<String>,
+    eventlog: Option<String>,
+    log: Option<String>,
+    http: Option<String>,
+    listen: Option<String>,
+    state: Option<String>,
+    trinomial: Option<SPRTSettings>,
+    pentanomial: Option<SPRTSettings>,
+    tune: Option<TuneConfig>,
+    verbose: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QueueConfig {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    engines: HashMap<String, EngineConfig>,
+    threads: Option<usize>,
+    active: Option<usize>,
+    schedule: Option<String>,
+    // Each is a Config plus a name and priority
+    tests: Vec<serde_json::Value>,
+}
+
+fn read_file<T: DeserializeOwned>(path: &Path) -> Result<T, ParseError> {
+    let text = read_to_string(path).map_err(|e| {
+        ParseError::Config(format!("{}: failed to read file: {}", path.display(), e))
+    })?;
+
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+    if is_json {
+        serde_json::from_str(&text)
+            .map_err(|e| ParseError::Config(format!("{}: {}", path.display(), e)))
+    } else {
+        toml::from_str(&text).map_err(|e| ParseError::Config(format!("{}: {}", path.display(), e)))
+    }
+}
+
+fn read_config(path: &Path) -> Result<Config, ParseError> {
+    read_file(path)
+}
+
+// Engines from included files, then the file itself, with later definitions replacing earlier ones
+fn collect_engines(
+    path: &Path,
+    config: &Config,
+    depth: usize,
+) -> Result<HashMap<String, EngineConfig>, ParseError> {
+    if depth > 8 {
+        return Err(ParseError::Config(format!(
+            "{}: include: too many nested includes",
+            path.display()
+        )));
+    }
+
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut engines = HashMap::new();
+
+    for include in &config.include {
+        let include_path: PathBuf = dir.join(include);
+        let included = read_config(&include_path)?;
+        engines.extend(collect_engines(&include_path, &included, depth + 1)?);
+    }
+
+    engines.extend(config.engines.clone());
+
+    Ok(engines)
+}
+
+fn resolve_player(
+    path: &Path,
+    idx: usize,
+    player: &PlayerConfig,
+    engines: &HashMap<String, EngineConfig>,
+) -> Result<PlayerSettings, ParseError> {
+    let engine = match &player.engine {
+        Some(engine_name) => match engines.get(engine_name) {
+            Some(engine) => EngineConfig {
+                name: engine.name.clone().or(Some(engine_name.clone())),
+                ..engine.clone()
+            },
+            None => {
+                return Err(ParseError::Config(format!(
+                    "{}: players[{}].engine: unknown engine \"{}\"",
+                    path.display(),
+                    idx,
+                    engine_name
+                )));
+            }
+        },
+        None => EngineConfig::default(),
+    };
+
+    let settings = PlayerSettings {
+        name: player.name.clone().or(engine.name).unwrap_or_default(),
+        path: player.path.clone().or(engine.path).unwrap_or_default(),
+        proto: player.proto.clone().or(engine.proto).unwrap_or_default(),
+        parameters: player
+            .parameters
+            .clone()
+            .or(engine.parameters)
+            .unwrap_or_default(),
+        prefix: player.prefix.clone().or(engine.prefix).unwrap_or_default(),
+        tc: match player.tc.as_ref().or(engine.tc.as_ref()) {
+            Some(tc) => Some(parse_tc(tc).map_err(|e| {
+                ParseError::Config(format!("{}: players[{}].tc: {}", path.display(), idx, e))
+            })?),
+            None => None,
+        },
+        movetime: player.movetime.or(engine.movetime),
+        depth: player.depth.or(engine.depth),
+        nodes: player.nodes.or(engine.nodes),
+        ponder: player.ponder.or(engine.ponder).unwrap_or_default(),
+        debug: player.debug.or(engine.debug).unwrap_or_default(),
+    };
+
+    if settings.path.is_empty() && settings.is_engine() {
+        return Err(ParseError::Config(format!(
+            "{}: players[{}].path: missing value",
+            path.display(),
+            idx
+        )));
+    }
+
+    Ok(settings)
+}
+
+pub fn load(path: &Path) -> Result<TournamentSettings, ParseError> {
+    let config = read_config(path)?;
+    let engines = collect_engines(path, &config, 0)?;
+    resolve(path, config, &engines)
+}
+
+// Files named in a config are relative to the config, not to wherever the tournament is started from
+#[must_use]
+fn relative_to(dir: &Path, path: &str) -> String {
+    dir.join(path).to_string_lossy().into_owned()
+}
+
+fn resolve(
+    path: &Path,
+    config: Config,
+    engines: &HashMap<String, EngineConfig>,
+) -> Result<TournamentSettings, ParseError> {
+    let mut settings = TournamentSettings::default();
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    for (idx, player) in config.players.iter().enumerate() {
+        settings
+            .players
+            .push(resolve_player(path, idx, player, engines)?);
+    }
+
+    if let Some(game) = config.game {
+        settings.game = game.to_lowercase();
+    }
+    if let Some(openings) = config.openings {
+        let openings = relative_to(&dir, &openings);
+        let file = read_to_string(&openings).map_err(|e| {
+            ParseError::Config(format!(
+                "{}: openings: failed to read {}: {}",
+                path.display(),
+                openings,
+                e
+            ))
+        })?;
+        settings.fens = file.lines().map(String::from).collect();
+    }
+    if config.games.is_some() {
+        settings.num_games = config.games;
+    }
+    if let Some(threads) = config.threads {
+        settings.num_threads = threads;
+    }
+    if let Some(timemargin) = config.timemargin {
+        settings.timing.margin = u128::from(timemargin);
+    }
+    if let Some(stalltime) = config.stalltime {
+        settings.timing.stall_time = u128::from(stalltime);
+    }
+    if let Some(enginetime) = config.enginetime {
+        settings.timing.trust_engine = enginetime;
+    }
+    if let Some(affinity) = config.affinity {
+        settings.affinity = Some(
+            parse_affinity(&affinity)
+                .map_err(|e| ParseError::Config(format!("{}: affinity: {}", path.display(), e)))?,
+        );
+    }
+    if let Some(updates) = config.updates {
+        settings.update_frequency = updates;
+    }
+    if let Some(pgn) = config.pgn {
+        settings.pgn = Some(relative_to(&dir, &pgn));
+    }
+    if let Some(results_json) = config.results_json {
+        settings.results_json = Some(relative_to(&dir, &results_json));
+    }
+    if let Some(results_csv) = config.results_csv {
+        settings.results_csv = Some(relative_to(&dir, &results_csv));
+    }
+    if let Some(eventlog) = config.eventlog {
+        settings.eventlog = Some(relative_to(&dir, &eventlog));
+    }
+    if let Some(log) = config.log {
+        settings.log = Some(relative_to(&dir, &log));
+    }
+    if config.http.is_some() {
+        settings.http = config.http;
+    }
+    if config.listen.is_some() {
+        settings.listen = config.listen;
+    }
+    if let Some(state) = config.state {
+        settings.state = Some(relative_to(&dir, &state));
+    }
+    if config.trinomial.is_some() {
+        settings.sprt_trinomial = config.trinomial;
+    }
+    if config.pentanomial.is_some() {
+        settings.sprt_pentanomial = config.pentanomial;
+    }
+    if let Some(tune) = config.tune {
+        settings.tune = Some(TuneSettings {
+            params: read_spec(&relative_to(&dir, &tune.spec))?,
+            output: tune.output.map(|output| relative_to(&dir, &output)),
+        });
+    }
+    if let Some(verbose) = config.verbose {
+        settings.verbose = verbose;
+    }
+
+    Ok(settings)
+}
+
+pub fn load_queue(path: &Path) -> Result<QueueSettings, ParseError> {
+    let queue: QueueConfig = read_file(path)?;
+    let mut settings = QueueSettings::default();
+
+    // Engines shared by every test, tests can add their own
+    let shared = Config {
+        include: queue.include,
+        engines: queue.engines,
+        ..Default::default()
+    };
+    let shared = collect_engines(path, &shared, 0)?;
+
+    if let Some(threads) = queue.threads {
+        settings.num_threads = threads;
+    }
+    if let Some(active) = queue.active {
+        settings.active = active;
+    }
+    settings.schedule = match queue.schedule.as_deref() {
+        None | Some("round-robin") => Schedule::RoundRobin,
+        Some("priority") => Schedule::Priority,
+        Some(schedule) => {
+            return Err(ParseError::Config(format!(
+                "{}: schedule: expected round-robin or priority, got \"{}\"",
+                path.display(),
+                schedule
+            )));
+        }
+    };
+
+    for (idx, mut test) in queue.tests.into_iter().enumerate() {
+        let error = |e: &dyn std::fmt::Display| {
+            ParseError::Config(format!("{}: tests[{}]: {}", path.display(), idx, e))
+        };
+
+        let (name, priority) = match test.as_object_mut() {
+            Some(table) => (table.remove("name"), table.remove("priority")),
+            None => return Err(error(&"expected a table")),
+        };
+        let name = match name {
+            Some(name) => name
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| error(&"name: expected a string"))?,
+            None => format!("Test {}", idx + 1),
+        };
+        let priority = match priority {
+            Some(priority) => priority
+                .as_u64()
+                .map(|priority| priority as usize)
+                .ok_or_else(|| error(&"priority: expected a whole number"))?,
+            None => 1,
+        };
+
+        let config: Config = serde_json::from_value(test).map_err(|e| error(&e))?;
+        let mut engines = shared.clone();
+        engines.extend(collect_engines(path, &config, 0)?);
+
+        settings.tests.push(QueueTest {
+            name,
+            priority,
+            settings: resolve(path, config, &engines)?,
+        });
+    }
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::players::clock::ClockType;
+    use crate::tournament::{Affinity, TimingSettings};
+    use std::fs::{create_dir_all, write};
+
+    fn write_files(dir: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(dir);
+        create_dir_all(&dir).unwrap();
+        for (name, text) in files {
+            write(dir.join(name), text).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn success() {
+        let dir = write_files(
+            "cutergames_config_success",
+            &[
+                (
+                    "engines.toml",
+                    "[engines.base]\n\
+                     path = \"./base\"\n\
+                     proto = \"ugi\"\n\
+                     prefix = \"nice -n 5\"\n\
+                     depth = 8\n\
+                     tc = \"40/60+0.5\"\n",
+                ),
+                (
+                    "tournament.toml",
+                    "include = [\"engines.toml\"]\n\
+                     threads = 4\n\
+                     timemargin = 50\n\
+                     affinity = \"auto\"\n\
+                     games = 100\n\
+                     \n\
+                     [engines.dev]\n\
+                     name = \"Dev\"\n\
+                     path = \"./dev\"\n\
+                     proto = \"uai\"\n\
+                     \n\
+                     [[players]]\n\
+                     engine = \"dev\"\n\
+                     debug = true\n\
+                     parameters = \"--hash 16\"\n\
+                     \n\
+                     [[players]]\n\
+                     engine = \"base\"\n\
+                     \n\
+                     [trinomial]\n\
+                     alpha = 0.05\n\
+                     beta = 0.05\n\
+                     elo0 = 0.0\n\
+                     elo1 = 5.0\n\
+                     autostop = true\n",
+                ),
+            ],
+        );
+
+        assert_eq!(
+            load(&dir.join("tournament.toml")),
+            Ok(TournamentSettings {
+                players: vec![
+                    PlayerSettings {
+                        name: "Dev".to_string(),
+                        path: "./dev".to_string(),
+                        proto: "uai".to_string(),
+                        parameters: "--hash 16".to_string(),
+                        prefix: "".to_string(),
+                        tc: None,
+                        movetime: None,
+                        depth: None,
+                        nodes: None,
+                        ponder: false,
+                        debug: true,
+                    },
+                    PlayerSettings {
+                        name: "base".to_string(),
+                        path: "./base".to_string(),
+                        proto: "ugi".to_string(),
+                        parameters: "".to_string(),
+                        prefix: "nice -n 5".to_string(),
+                        tc: Some(ClockType::Repeating {
+                            moves: 40,
+                            base: 60000,
+                            increment: 500,
+                            remaining: 60000,
+                            left: 40,
+                        }),
+                        movetime: None,
+                        depth: Some(8),
+                        nodes: None,
+                        ponder: false,
+                        debug: false,
+                    },
+                ],
+                num_threads: 4,
+                timing: TimingSettings {
+                    margin: 50,
+                    trust_engine: false,
+                    stall_time: 60000,
+                },
+                affinity: Some(Affinity::Auto),
+                num_games: Some(100),
+                sprt_trinomial: Some(SPRTSettings {
+                    alpha: 0.05,
+                    beta: 0.05,
+                    elo0: 0.0,
+                    elo1: 5.0,
+                    autostop: true,
+                }),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn json() {
+        let dir = write_files(
+            "cutergames_config_json",
+            &[(
+                "tournament.json",
+                "{\"threads\": 2, \"players\": [{\"name\": \"A\", \"path\": \"./a\"}]}",
+            )],
+        );
+
+        let settings = load(&dir.join("tournament.json")).unwrap();
+        assert_eq!(settings.num_threads, 2);
+        assert_eq!(settings.players.len(), 1);
+        assert_eq!(settings.players[0].path, "./a");
+    }
+
+    #[test]
+    fn queue() {
+        let dir = write_files(
+            "cutergames_config_queue",
+            &[(
+                "queue.toml",
+                "threads = 8\n\
+                 active = 2\n\
+                 schedule = \"priority\"\n\
+                 \n\
+                 [engines.base]\n\
+                 path = \"./base\"\n\
+                 proto = \"ugi\"\n\
+                 \n\
+                 [[tests]]\n\
+                 name = \"LMR\"\n\
+                 priority = 3\n\
+                 games = 100\n\
+                 players = [{ name = \"Dev\", path = \"./dev\", proto = \"ugi\" }, { engine = \"base\" }]\n\
+                 \n\
+                 [[tests]]\n\
+                 players = [{ engine = \"base\" }, { engine = \"base\", name = \"Base 2\" }]\n",
+            )],
+        );
+
+        let queue = load_queue(&dir.join("queue.toml")).unwrap();
+        assert_eq!(queue.num_threads, 8);
+        assert_eq!(queue.active, 2);
+        assert_eq!(queue.schedule, Schedule::Priority);
+        assert_eq!(queue.tests.len(), 2);
+        assert_eq!(queue.tests[0].name, "LMR");
+        assert_eq!(queue.tests[0].priority, 3);
+        assert_eq!(queue.tests[0].settings.num_games, Some(100));
+        assert_eq!(queue.tests[0].settings.players[1].path, "./base");
+        assert_eq!(queue.tests[1].name, "Test 2");
+        assert_eq!(queue.tests[1].priority, 1);
+        assert_eq!(queue.tests[1].settings.players[1].name, "Base 2");
+
+        let dir = write_files(
+            "cutergames_config_queue_failure",
+            &[
+                ("schedule.toml", "schedule = \"fifo\"\ntests = []\n"),
+                ("unknown_key.toml", "[[tests]]\nthraeds = 4\n"),
+            ],
+        );
+        let error = |name: &str| match load_queue(&dir.join(name)) {
+            Err(ParseError::Config(msg)) => msg,
+            _ => panic!("Expected config error for {}", name),
+        };
+        assert!(error("schedule.toml").contains("fifo"));
+        assert!(error("unknown_key.toml").contains("tests[0]"));
+    }
+
+    #[test]
+    fn relative_paths() {
+        // The files sit next to the config, not in the directory the tests run from
+        let dir = write_files(
+            "cutergames_config_relative",
+            &[
+                ("openings.txt", "startpos\nx5o/7/7/7/7/7/o5x o 0 1\n"),
+                (
+                    "tournament.toml",
+                    "openings = \"openings.txt\"\n\
+                     pgn = \"games.pgn\"\n\
+                     state = \"/tmp/state.json\"\n",
+                ),
+            ],
+        );
+
+        let settings = load(&dir.join("tournament.toml")).unwrap();
+        assert_eq!(
+            settings.fens,
+            vec![
+                "startpos".to_string(),
+                "x5o/7/7/7/7/7/o5x o 0 1".to_string()
+            ]
+        );
+        assert_eq!(
+            settings.pgn,
+            Some(dir.join("games.pgn").to_string_lossy().into_owned())
+        );
+        assert_eq!(settings.state, Some("/tmp/state.json".to_string()));
+    }
+
+    #[test]
+    fn failure() {
+        let dir = write_files(
+            "cutergames_config_failure",
+            &[
+                ("unknown_key.toml", "threads = 4\nthraeds = 4\n"),
+                ("unknown_engine.toml", "[[players]]\nengine = \"nope\"\n"),
+                ("missing_path.toml", "[[players]]\nname = \"A\"\n"),
+                ("bad_value.toml", "threads = \"four\"\n"),
+            ],
+        );
+
+        let error = |name: &str| match load(&dir.join(name)) {
+            Err(ParseError::Config(msg)) => msg,
+            _ => panic!("Expected config error for {}", name),
+        };
+
+        assert!(error("unknown_key.toml").contains("thraeds"));
+        assert!(error("unknown_engine.toml").contains("players[0].engine"));
+        assert!(error("missing_path.toml").contains("players[0].path"));
+        assert!(error("bad_value.toml").contains("threads"));
+        assert!(error("does_not_exist.toml").contains("failed to read"));
+    }
+}
@@ -0,0 +1,15 @@
+mod config;
+mod elo;
+pub mod parse;
+pub mod players;
+mod sprt;
+mod stats;
+mod store;
+pub mod tournament;
+
+pub use crate::players::Player;
+pub use crate::tournament::{
+    Event, GameData, MoveTiming, Observer, PlayerSettings, QueueSettings, QueueTest, SPRTSettings,
+    Schedule, TimingSettings, TournamentSettings, results::Results, run::run, run_queue,
+    run_worker,
+};
@@ -1,35 +1,192 @@
-use crate::parse::parse;
-use std::env;
+use cutergames::{
+    Results,
+    parse::{parse, read_queue, validate},
+    run, run_queue, run_worker,
+};
+use std::{env, process::ExitCode};
 
-mod elo;
-mod parse;
-mod players;
-mod sprt;
-mod stats;
-mod store;
-mod tournament;
+const USAGE: &str = "\
+Usage: cutergames [OPTIONS] --player ... --player ...
+       cutergames --worker HOST:PORT [--threads N] [--log DIR]
+       cutergames --queue FILE
 
-fn main() -> Result<(), ()> {
-    let args = env::args().collect::<Vec<String>>();
+Players:
+  --player name=NAME path=PATH proto=PROTO [parameters=PARAMS] [prefix=COMMAND]
+           [tc=TC] [movetime=MS] [depth=N] [nodes=N] [ponder] [debug]
+                            Add a player. PROTO is one of ugi, uai, human or builtin
+                            (name=random, greedy or minimax), TC is like 40/60+0.5
+
+Tournament:
+  --config FILE             Read settings from a TOML or JSON file
+  --game NAME               The game to play (default: ataxx)
+  --fens FILE               Openings to play, one per line
+  --games N                 Stop after N games
+  --threads N               Games to play simultaneously (default: 1)
+  --timemargin MS           Extra time engines may use before they lose on time (default: 10)
+  --stalltime MS            How long engines without a time limit may think (default: 60000)
+  --enginetime              Charge engines the time they report when it fits what was measured
+  --affinity auto|CORES     Pin each thread's engines to their own cores, such as 0-7
+  --trinomial alpha=A beta=B elo0=E0 elo1=E1 [autostop]
+                            SPRT settings
+  --tune spec=FILE [output=FILE]
+                            Tune engine options with SPSA, writing the values to FILE
+
+Output:
+  --updates N               Print results every N games (default: 10)
+  --pgn FILE                Append finished games to FILE
+  --results-json FILE       Write the results to FILE as JSON on every update
+  --results-csv FILE        Write the results to FILE as CSV on every update
+  --eventlog FILE           Append every tournament event to FILE as JSON lines
+  --log DIR                 Write each engine's communication to a file per game in DIR
+  --http ADDRESS            Serve a status page on ADDRESS, such as 127.0.0.1:8080
+  --state FILE              Save the tournament state to FILE
+  --resume                  Continue the tournament saved with --state
+  --verbose                 Print every tournament event
+
+Queue:
+  --queue FILE              Play the tests in FILE, sharing the threads between them
+
+Distributed:
+  --listen ADDRESS          Hand out games to workers connecting to ADDRESS, such as 0.0.0.0:9000,
+                            or to PORT on this machine only. Workers aren't authenticated,
+                            only listen on networks you trust
+  --worker HOST:PORT        Play games for the coordinator at HOST:PORT, --threads at a time.
+                            The coordinator picks the programs run, only connect to one you trust
+
+Other:
+  --help                    Print this message
+  --version                 Print the version
+  --about                   Print information about CuterGames";
+
+fn print_results(results: &Results) {
+    println!();
+    println!("Match Statistics:");
+    println!("Game: {}", results.game);
+    println!("Games played: {}", results.games_completed);
+    println!("Duration: {:.1}s", results.elapsed_seconds);
+
+    println!();
+    println!(
+        "{:<3} {:<12} {:>5} {:>5} {:>5} {:>5} {:>8} {:>8}",
+        "id", "name", "games", "w", "l", "d", "elo", "err"
+    );
+    for player in &results.players {
+        println!(
+            "{:<3} {:<12} {:>5} {:>5} {:>5} {:>5} {:>8.2} {:>8.2}",
+            player.id,
+            player.name,
+            player.played,
+            player.w,
+            player.l,
+            player.d,
+            player.elo,
+            player.elo_err
+        );
+    }
+
+    println!();
+    println!("+2 +1 +0 -1 -2");
+    for player in &results.players {
+        let penta = &player.pentanomial;
+        println!(
+            "{:>2} {:>2} {:>2} {:>2} {:>2} {}",
+            penta.ww, penta.wd_dw, penta.wl_lw_dd, penta.ld_dl, penta.ll, player.name
+        );
+    }
+
+    for sprt in &results.sprt {
+        println!();
+        println!(
+            "SPRT ({}): LLR {:.2} ({:.2}, {:.2}) {:?}",
+            sprt.kind, sprt.llr, sprt.lbound, sprt.ubound, sprt.status
+        );
+    }
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<String>>();
 
     if args.contains(&"--help".to_string()) {
-        Ok(())
+        println!("CuterGames {}", env!("CARGO_PKG_VERSION"));
+        println!();
+        println!("{}", USAGE);
+        ExitCode::SUCCESS
     } else if args.contains(&"--version".to_string()) {
         println!("CuterGames {}", env!("CARGO_PKG_VERSION"));
-        Ok(())
+        ExitCode::SUCCESS
     } else if args.contains(&"--about".to_string()) {
         println!("CuterGames {}", env!("CARGO_PKG_VERSION"));
         println!(env!("CARGO_PKG_DESCRIPTION"));
         println!(env!("CARGO_PKG_HOMEPAGE"));
-        Ok(())
-    } else if let Ok(settings) = parse(args.into_iter()) {
+        ExitCode::SUCCESS
+    } else {
+        let mut settings = match parse(args.into_iter()) {
+            Ok(settings) => settings,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                eprintln!("Run with --help for usage");
+                return ExitCode::FAILURE;
+            }
+        };
+
+        if let Err(err) = validate(&settings) {
+            eprintln!("Error: {}", err);
+            return ExitCode::FAILURE;
+        }
+
         if settings.verbose {
             println!("{:#?}", settings);
         }
+        settings.interactive = true;
 
-        tournament::run::run(&settings);
-        Ok(())
-    } else {
-        Err(())
+        if let Some(path) = &settings.queue {
+            let mut queue = match read_queue(path) {
+                Ok(queue) => queue,
+                Err(err) => {
+                    eprintln!("Error: {}", err);
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            queue.interactive = true;
+            return match run_queue(&queue) {
+                Ok(results) => {
+                    for (test, result) in queue.tests.iter().zip(results) {
+                        println!();
+                        println!("Test {}:", test.name);
+                        match result {
+                            Ok(results) => print_results(&results),
+                            Err(err) => println!("{}", err),
+                        }
+                    }
+                    ExitCode::SUCCESS
+                }
+                Err(err) => {
+                    eprintln!("Error: {}", err);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+
+        if settings.worker.is_some() {
+            return match run_worker(&settings) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(err) => {
+                    eprintln!("Error: {}", err);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+
+        match run(&settings, vec![]) {
+            Ok(results) => {
+                print_results(&results);
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                ExitCode::FAILURE
+            }
+        }
     }
 }
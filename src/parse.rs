@@ -1,69 +1,175 @@
-use crate::tournament::{PlayerSettings, SPRTSettings, TournamentSettings};
-use std::{borrow::Borrow, env, fs::read_to_string, iter::Peekable, path::Path};
+use crate::{
+    config,
+    players::{Protocol, builtin::get_strategy, clock::parse_tc, get_protocol},
+    tournament::{
+        MAX_THREADS, PlayerSettings, QueueSettings, SPRTSettings, TournamentSettings,
+        TuneParameter, TuneSettings, check_affinity, parse_affinity, parse_spec,
+    },
+};
+use std::{
+    borrow::Borrow,
+    env,
+    fs::read_to_string,
+    net::{SocketAddr, ToSocketAddrs},
+    path::Path,
+};
 
 #[derive(PartialEq, Debug)]
 pub enum ParseError {
     ValueParse(String),
-    FileParse,
-    MissingParameter,
-    UnknownParameter,
+    FileParse(String),
+    Config(String),
+    MissingParameter(String),
+    UnknownParameter(String),
+    Invalid(String),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::ValueParse(msg) => write!(f, "{}", msg),
+            ParseError::FileParse(path) => write!(f, "Failed to read file: {}", path),
+            ParseError::Config(msg) => write!(f, "Config error: {}", msg),
+            ParseError::MissingParameter(msg) => write!(f, "Missing parameter: {}", msg),
+            ParseError::UnknownParameter(msg) => write!(f, "Unknown parameter: {}", msg),
+            ParseError::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+const FLAGS: [&str; 3] = ["--verbose", "--resume", "--enginetime"];
+const SINGLES: [&str; 19] = [
+    "--config",
+    "--games",
+    "--timemargin",
+    "--stalltime",
+    "--threads",
+    "--affinity",
+    "--updates",
+    "--game",
+    "--pgn",
+    "--results-json",
+    "--results-csv",
+    "--eventlog",
+    "--log",
+    "--http",
+    "--listen",
+    "--worker",
+    "--queue",
+    "--state",
+    "--fens",
+];
+const MULTIPLES: [&str; 3] = ["--player", "--trinomial", "--tune"];
+
+fn parse_usize(flag: &str, value: &str) -> Result<usize, ParseError> {
+    value.parse::<usize>().map_err(|_| {
+        ParseError::ValueParse(format!(
+            "{} expects a whole number, got \"{}\"",
+            flag, value
+        ))
+    })
+}
+
+fn parse_f32(flag: &str, key: &str, value: &str) -> Result<f32, ParseError> {
+    value.parse::<f32>().map_err(|_| {
+        ParseError::ValueParse(format!(
+            "{} {} expects a number, got \"{}\"",
+            flag, key, value
+        ))
+    })
+}
+
+pub(crate) fn read_spec(path: &str) -> Result<Vec<TuneParameter>, ParseError> {
+    let text = read_to_string(path).map_err(|_| ParseError::FileParse(path.to_string()))?;
+    parse_spec(&text).map_err(|msg| ParseError::Invalid(format!("--tune {}: {}", path, msg)))
 }
 
 pub fn parse<T>(iter: impl Iterator<Item = T>) -> Result<TournamentSettings, ParseError>
 where
     T: Borrow<str> + AsRef<Path>,
 {
-    let mut settings = TournamentSettings::default();
-    let mut iter = iter.peekable();
+    let words = iter.collect::<Vec<T>>();
+
+    // Settings from a config file are overridden by the command line
+    let config_path = words
+        .windows(2)
+        .find(|pair| pair[0].borrow() == "--config")
+        .map(|pair| &pair[1]);
+    let mut settings = if let Some(path) = config_path {
+        config::load(path.as_ref())?
+    } else {
+        TournamentSettings::default()
+    };
+    let mut cli_players = vec![];
+
+    let mut iter = words.into_iter().peekable();
 
     while let Some(word) = iter.next() {
+        let is_known = FLAGS.contains(&word.borrow())
+            || SINGLES.contains(&word.borrow())
+            || MULTIPLES.contains(&word.borrow());
+        if word.borrow().starts_with("--") && !is_known {
+            return Err(ParseError::UnknownParameter(word.borrow().to_string()));
+        }
+
         // Flags
         match word.borrow() {
             "--verbose" => settings.verbose = true,
-            // "--debug" => settings.debug = true,
+            "--resume" => settings.resume = true,
+            "--enginetime" => settings.timing.trust_engine = true,
             _ => {}
         }
 
         // Singles
-        match (word.borrow(), iter.peek()) {
-            ("--games", Some(next)) => {
-                if let Ok(value) = next.borrow().parse::<usize>() {
-                    settings.num_games = Some(value);
-                } else {
-                    return Err(ParseError::ValueParse(format!(
-                        "Failed to parse usize: {}",
-                        next.borrow()
+        if SINGLES.contains(&word.borrow()) {
+            let next = match iter.next() {
+                Some(next) if !next.borrow().starts_with("--") => next,
+                _ => {
+                    return Err(ParseError::MissingParameter(format!(
+                        "{} expects a value",
+                        word.borrow()
                     )));
                 }
-            }
-            ("--threads", Some(next)) => {
-                if let Ok(value) = next.borrow().parse::<usize>() {
-                    settings.num_threads = value;
-                } else {
-                    return Err(ParseError::ValueParse(format!(
-                        "Failed to parse usize: {}",
-                        next.borrow()
-                    )));
+            };
+
+            match word.borrow() {
+                "--games" => settings.num_games = Some(parse_usize(word.borrow(), next.borrow())?),
+                "--timemargin" => {
+                    settings.timing.margin = parse_usize(word.borrow(), next.borrow())? as u128
                 }
-            }
-            ("--updates", Some(next)) => {
-                if let Ok(value) = next.borrow().parse::<usize>() {
-                    settings.update_frequency = value;
-                } else {
-                    return Err(ParseError::ValueParse(format!(
-                        "Failed to parse usize: {}",
-                        next.borrow()
-                    )));
+                "--stalltime" => {
+                    settings.timing.stall_time = parse_usize(word.borrow(), next.borrow())? as u128
                 }
-            }
-            ("--fens", Some(next)) => {
-                if let Ok(file) = read_to_string(next) {
-                    settings.fens = file.lines().map(String::from).collect();
-                } else {
-                    return Err(ParseError::FileParse);
+                "--threads" => settings.num_threads = parse_usize(word.borrow(), next.borrow())?,
+                "--affinity" => {
+                    settings.affinity = Some(
+                        parse_affinity(next.borrow())
+                            .map_err(|e| ParseError::ValueParse(format!("--affinity {}", e)))?,
+                    )
                 }
+                "--updates" => {
+                    settings.update_frequency = parse_usize(word.borrow(), next.borrow())?
+                }
+                "--game" => settings.game = next.borrow().to_lowercase(),
+                "--pgn" => settings.pgn = Some(next.borrow().to_string()),
+                "--results-json" => settings.results_json = Some(next.borrow().to_string()),
+                "--results-csv" => settings.results_csv = Some(next.borrow().to_string()),
+                "--eventlog" => settings.eventlog = Some(next.borrow().to_string()),
+                "--log" => settings.log = Some(next.borrow().to_string()),
+                "--http" => settings.http = Some(next.borrow().to_string()),
+                "--listen" => settings.listen = Some(next.borrow().to_string()),
+                "--worker" => settings.worker = Some(next.borrow().to_string()),
+                "--queue" => settings.queue = Some(next.borrow().to_string()),
+                "--state" => settings.state = Some(next.borrow().to_string()),
+                "--fens" => {
+                    if let Ok(file) = read_to_string(&next) {
+                        settings.fens = file.lines().map(String::from).collect();
+                    } else {
+                        return Err(ParseError::FileParse(next.borrow().to_string()));
+                    }
+                }
+                _ => {}
             }
-            _ => {}
         }
 
         // Multiples
@@ -71,22 +177,56 @@ where
             "--player" => {
                 let mut player = PlayerSettings::default();
                 while let Some(asd) = iter.peek() {
+                    if asd.borrow() == "ponder" {
+                        player.ponder = true;
+                        iter.next();
+                        continue;
+                    }
+                    if asd.borrow() == "debug" {
+                        player.debug = true;
+                        iter.next();
+                        continue;
+                    }
                     match asd.borrow().split_once('=') {
-                        Some((_, "")) => return Err(ParseError::MissingParameter),
+                        Some((key, "")) => {
+                            return Err(ParseError::MissingParameter(format!(
+                                "--player {} expects a value",
+                                key
+                            )));
+                        }
                         Some(("name", second)) => player.name = second.to_string(),
                         Some(("path", second)) => player.path = second.to_string(),
                         Some(("proto", second)) => player.proto = second.to_string(),
                         Some(("parameters", second)) => player.parameters = second.to_string(),
-                        Some((_, _)) => return Err(ParseError::UnknownParameter),
+                        Some(("prefix", second)) => player.prefix = second.to_string(),
+                        Some(("tc", second)) => {
+                            player.tc = Some(parse_tc(second).map_err(|e| {
+                                ParseError::ValueParse(format!("--player tc {}", e))
+                            })?)
+                        }
+                        Some(("movetime", second)) => {
+                            player.movetime = Some(parse_usize("--player movetime", second)?)
+                        }
+                        Some(("depth", second)) => {
+                            player.depth = Some(parse_usize("--player depth", second)?)
+                        }
+                        Some(("nodes", second)) => {
+                            player.nodes = Some(parse_usize("--player nodes", second)?)
+                        }
+                        Some((key, _)) => {
+                            return Err(ParseError::UnknownParameter(format!("--player {}", key)));
+                        }
                         _ => break,
                     }
 
                     iter.next();
                 }
-                if player.path.is_empty() {
-                    return Err(ParseError::MissingParameter);
+                if player.path.is_empty() && player.is_engine() {
+                    return Err(ParseError::MissingParameter(
+                        "--player expects path=".to_string(),
+                    ));
                 }
-                settings.players.push(player);
+                cli_players.push(player);
             }
             "--trinomial" => {
                 let mut tri = SPRTSettings::default();
@@ -103,11 +243,23 @@ where
                         {
                             match asd.borrow().split_once('=') {
                                 Some(("alpha", second)) => {
-                                    tri.alpha = second.parse::<f32>().unwrap()
+                                    tri.alpha = parse_f32("--trinomial", "alpha", second)?
+                                }
+                                Some(("beta", second)) => {
+                                    tri.beta = parse_f32("--trinomial", "beta", second)?
+                                }
+                                Some(("elo0", second)) => {
+                                    tri.elo0 = parse_f32("--trinomial", "elo0", second)?
+                                }
+                                Some(("elo1", second)) => {
+                                    tri.elo1 = parse_f32("--trinomial", "elo1", second)?
+                                }
+                                Some((key, _)) => {
+                                    return Err(ParseError::UnknownParameter(format!(
+                                        "--trinomial {}",
+                                        key
+                                    )));
                                 }
-                                Some(("beta", second)) => tri.beta = second.parse::<f32>().unwrap(),
-                                Some(("elo0", second)) => tri.elo0 = second.parse::<f32>().unwrap(),
-                                Some(("elo1", second)) => tri.elo1 = second.parse::<f32>().unwrap(),
                                 _ => break,
                             }
                             iter.next();
@@ -116,16 +268,345 @@ where
                 }
                 settings.sprt_trinomial = Some(tri);
             }
+            "--tune" => {
+                let mut tune = TuneSettings::default();
+                let mut spec = None;
+                while let Some(asd) = iter.peek() {
+                    match asd.borrow().split_once('=') {
+                        Some(("spec", second)) => spec = Some(second.to_string()),
+                        Some(("output", second)) => tune.output = Some(second.to_string()),
+                        Some((key, _)) => {
+                            return Err(ParseError::UnknownParameter(format!("--tune {}", key)));
+                        }
+                        _ => break,
+                    }
+                    iter.next();
+                }
+                let Some(spec) = spec else {
+                    return Err(ParseError::MissingParameter(
+                        "--tune expects spec=".to_string(),
+                    ));
+                };
+                tune.params = read_spec(&spec)?;
+                settings.tune = Some(tune);
+            }
             _ => {}
         }
     }
 
+    // Players on the command line replace any from the config file
+    if !cli_players.is_empty() {
+        settings.players = cli_players;
+    }
+
+    if settings.resume && settings.state.is_none() {
+        return Err(ParseError::MissingParameter(
+            "--resume requires --state".to_string(),
+        ));
+    }
+
     Ok(settings)
 }
 
+#[must_use]
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+// Paths without a directory are looked up the same way the engine will be launched
+#[must_use]
+fn find_executable(path: &str) -> bool {
+    let path = Path::new(path);
+    if path.components().count() > 1 {
+        return is_executable(path);
+    }
+
+    is_executable(path)
+        || env::var_os("PATH")
+            .is_some_and(|dirs| env::split_paths(&dirs).any(|dir| is_executable(&dir.join(path))))
+}
+
+fn validate_sprt(flag: &str, sprt: &SPRTSettings) -> Result<(), ParseError> {
+    if !(sprt.alpha > 0.0 && sprt.alpha < 1.0) {
+        return Err(ParseError::Invalid(format!(
+            "{} alpha must be between 0 and 1, got {}",
+            flag, sprt.alpha
+        )));
+    }
+    if !(sprt.beta > 0.0 && sprt.beta < 1.0) {
+        return Err(ParseError::Invalid(format!(
+            "{} beta must be between 0 and 1, got {}",
+            flag, sprt.beta
+        )));
+    }
+    if sprt.alpha + sprt.beta >= 1.0 {
+        return Err(ParseError::Invalid(format!(
+            "{} alpha + beta must be less than 1",
+            flag
+        )));
+    }
+    if sprt.elo0 >= sprt.elo1 {
+        return Err(ParseError::Invalid(format!(
+            "{} elo0 ({}) must be less than elo1 ({})",
+            flag, sprt.elo0, sprt.elo1
+        )));
+    }
+    Ok(())
+}
+
+fn validate_tune(settings: &TournamentSettings, tune: &TuneSettings) -> Result<(), ParseError> {
+    if settings.players.len() != 2 {
+        return Err(ParseError::Invalid(
+            "--tune requires exactly two players".to_string(),
+        ));
+    }
+    if settings.num_games.is_none_or(|games| games < 2) {
+        return Err(ParseError::Invalid(
+            "--tune requires --games of at least 2".to_string(),
+        ));
+    }
+    if settings.resume {
+        return Err(ParseError::Invalid(
+            "--tune can't be used with --resume".to_string(),
+        ));
+    }
+    if tune.params.is_empty() {
+        return Err(ParseError::Invalid(
+            "--tune spec has no parameters".to_string(),
+        ));
+    }
+    for param in &tune.params {
+        if !(param.min <= param.start && param.start <= param.max) {
+            return Err(ParseError::Invalid(format!(
+                "--tune {} start ({}) must be between min ({}) and max ({})",
+                param.name, param.start, param.min, param.max
+            )));
+        }
+        if param.c_end <= 0.0 || param.r_end <= 0.0 {
+            return Err(ParseError::Invalid(format!(
+                "--tune {} c_end and r_end must be positive",
+                param.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate(settings: &TournamentSettings) -> Result<(), ParseError> {
+    if let Some(affinity) = &settings.affinity {
+        if !cfg!(target_os = "linux") {
+            return Err(ParseError::Invalid(
+                "--affinity is only supported on Linux".to_string(),
+            ));
+        }
+        check_affinity(affinity).map_err(|e| ParseError::Invalid(format!("--affinity {}", e)))?;
+    }
+
+    // Each test is checked when the queue is read
+    if settings.queue.is_some() {
+        return Ok(());
+    }
+
+    // Everything else comes from the coordinator
+    if settings.worker.is_some() {
+        if settings.num_threads == 0 {
+            return Err(ParseError::Invalid(
+                "--threads must be at least 1".to_string(),
+            ));
+        }
+        if settings.listen.is_some() {
+            return Err(ParseError::Invalid(
+                "--worker can't be used with --listen".to_string(),
+            ));
+        }
+        return Ok(());
+    }
+
+    if settings.players.len() < 2 {
+        return Err(ParseError::Invalid(format!(
+            "At least two players are required, got {}",
+            settings.players.len()
+        )));
+    }
+
+    for player in &settings.players {
+        if get_protocol(&player.proto).is_none() {
+            return Err(ParseError::Invalid(format!(
+                "Player \"{}\" has unknown protocol \"{}\" (expected ugi, uai, human or builtin)",
+                player.name, player.proto
+            )));
+        }
+        if get_protocol(&player.proto) == Some(Protocol::UCI) {
+            return Err(ParseError::Invalid(format!(
+                "Player \"{}\" uses uci, which is not implemented yet",
+                player.name
+            )));
+        }
+        if player.is_human() {
+            if settings.num_threads != 1 || settings.listen.is_some() {
+                return Err(ParseError::Invalid(format!(
+                    "Player \"{}\" is human, which needs --threads 1 and no --listen",
+                    player.name
+                )));
+            }
+        } else if get_protocol(&player.proto) == Some(Protocol::Builtin) {
+            if get_strategy(&player.name).is_none() {
+                return Err(ParseError::Invalid(format!(
+                    "Builtin player \"{}\" is unknown (expected random, greedy or minimax)",
+                    player.name
+                )));
+            }
+        } else if !find_executable(&player.path) {
+            return Err(ParseError::Invalid(format!(
+                "Player \"{}\" path \"{}\" is not an executable file",
+                player.name, player.path
+            )));
+        }
+        if let Some(program) = player.prefix.split_whitespace().next()
+            && !find_executable(program)
+        {
+            return Err(ParseError::Invalid(format!(
+                "Player \"{}\" prefix \"{}\" is not an executable file",
+                player.name, program
+            )));
+        }
+        if player.tc.is_some() && player.movetime.is_some() {
+            return Err(ParseError::Invalid(format!(
+                "Player \"{}\" can't have both tc and movetime",
+                player.name
+            )));
+        }
+        for (key, value) in [
+            ("movetime", player.movetime),
+            ("depth", player.depth),
+            ("nodes", player.nodes),
+        ] {
+            if value == Some(0) {
+                return Err(ParseError::Invalid(format!(
+                    "Player \"{}\" {} must be at least 1",
+                    player.name, key
+                )));
+            }
+        }
+    }
+
+    if settings.fens.is_empty() {
+        return Err(ParseError::Invalid(
+            "No openings given, use --fens".to_string(),
+        ));
+    }
+
+    // A coordinator can leave every game to its workers
+    if settings.num_threads == 0 && settings.listen.is_none() {
+        return Err(ParseError::Invalid(
+            "--threads must be at least 1".to_string(),
+        ));
+    }
+    if settings.num_threads >= MAX_THREADS {
+        return Err(ParseError::Invalid(format!(
+            "--threads must be below {}",
+            MAX_THREADS
+        )));
+    }
+
+    if settings.update_frequency == 0 {
+        return Err(ParseError::Invalid(
+            "--updates must be at least 1".to_string(),
+        ));
+    }
+
+    if let Some(tri) = &settings.sprt_trinomial {
+        validate_sprt("--trinomial", tri)?;
+    }
+    if let Some(penta) = &settings.sprt_pentanomial {
+        validate_sprt("pentanomial", penta)?;
+    }
+    if let Some(tune) = &settings.tune {
+        validate_tune(settings, tune)?;
+    }
+
+    if let Some(address) = &settings.http
+        && !address
+            .to_socket_addrs()
+            .is_ok_and(|mut addresses| addresses.next().is_some())
+    {
+        return Err(ParseError::Invalid(format!(
+            "--http expects an address like localhost:8080, got \"{}\"",
+            address
+        )));
+    }
+
+    if let Some(address) = &settings.listen
+        && address.parse::<u16>().is_err()
+        && address.parse::<SocketAddr>().is_err()
+    {
+        return Err(ParseError::Invalid(format!(
+            "--listen expects a port or an address like 0.0.0.0:9000, got \"{}\"",
+            address
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn read_queue(path: &str) -> Result<QueueSettings, ParseError> {
+    let queue = config::load_queue(Path::new(path))?;
+
+    if queue.tests.is_empty() {
+        return Err(ParseError::Invalid(format!("Queue {} has no tests", path)));
+    }
+    if queue.active == 0 {
+        return Err(ParseError::Invalid(
+            "Queue active must be at least 1".to_string(),
+        ));
+    }
+    if queue.num_threads >= MAX_THREADS {
+        return Err(ParseError::Invalid(format!(
+            "Queue threads must be below {}",
+            MAX_THREADS
+        )));
+    }
+    if queue.num_threads < queue.active {
+        return Err(ParseError::Invalid(format!(
+            "Queue threads ({}) must be at least active ({})",
+            queue.num_threads, queue.active
+        )));
+    }
+
+    for test in &queue.tests {
+        if test.priority == 0 {
+            return Err(ParseError::Invalid(format!(
+                "Test {} priority must be at least 1",
+                test.name
+            )));
+        }
+        if test.settings.players.iter().any(|player| player.is_human()) {
+            return Err(ParseError::Invalid(format!(
+                "Test {} has a human player, which can't be queued",
+                test.name
+            )));
+        }
+        validate(&test.settings)
+            .map_err(|e| ParseError::Invalid(format!("Test {}: {}", test.name, e)))?;
+    }
+
+    Ok(queue)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::players::clock::ClockType;
+    use crate::tournament::{Affinity, TimingSettings};
 
     #[test]
     fn success() {
@@ -142,10 +623,41 @@ mod tests {
                     "path=test2",
                     "parameters=--one --two two",
                     "proto=test",
+                    "prefix=nice -n 5",
+                    "tc=40/60+0.5",
+                    "nodes=5000",
+                    "ponder",
+                    "debug",
                     "--games",
                     "123",
                     "--threads",
                     "4",
+                    "--timemargin",
+                    "25",
+                    "--stalltime",
+                    "5000",
+                    "--enginetime",
+                    "--affinity",
+                    "0-3",
+                    "--game",
+                    "Ataxx",
+                    "--pgn",
+                    "games.pgn",
+                    "--results-json",
+                    "results.json",
+                    "--results-csv",
+                    "results.csv",
+                    "--eventlog",
+                    "events.jsonl",
+                    "--log",
+                    "logs",
+                    "--http",
+                    "127.0.0.1:8080",
+                    "--listen",
+                    "0.0.0.0:9000",
+                    "--state",
+                    "state.json",
+                    "--resume",
                     "--verbose",
                 ]
                 .into_iter()
@@ -157,6 +669,12 @@ mod tests {
                         path: "test1".to_string(),
                         proto: "test".to_string(),
                         parameters: "Some thing long here".to_string(),
+                        prefix: "".to_string(),
+                        tc: None,
+                        movetime: None,
+                        depth: None,
+                        nodes: None,
+                        ponder: false,
                         debug: false,
                     },
                     PlayerSettings {
@@ -164,16 +682,48 @@ mod tests {
                         path: "test2".to_string(),
                         proto: "test".to_string(),
                         parameters: "--one --two two".to_string(),
-                        debug: false,
+                        prefix: "nice -n 5".to_string(),
+                        tc: Some(ClockType::Repeating {
+                            moves: 40,
+                            base: 60000,
+                            increment: 500,
+                            remaining: 60000,
+                            left: 40,
+                        }),
+                        movetime: None,
+                        depth: None,
+                        nodes: Some(5000),
+                        ponder: true,
+                        debug: true,
                     }
                 ],
+                game: "ataxx".to_string(),
                 fens: vec![],
                 num_threads: 4,
                 num_games: Some(123),
+                timing: TimingSettings {
+                    margin: 25,
+                    trust_engine: true,
+                    stall_time: 5000,
+                },
                 update_frequency: 10,
                 sprt_trinomial: None,
                 sprt_pentanomial: None,
+                tune: None,
+                pgn: Some("games.pgn".to_string()),
+                results_json: Some("results.json".to_string()),
+                results_csv: Some("results.csv".to_string()),
+                eventlog: Some("events.jsonl".to_string()),
+                log: Some("logs".to_string()),
+                http: Some("127.0.0.1:8080".to_string()),
+                listen: Some("0.0.0.0:9000".to_string()),
+                worker: None,
+                queue: None,
+                affinity: Some(Affinity::Cores(vec![0, 1, 2, 3])),
+                state: Some("state.json".to_string()),
+                resume: true,
                 verbose: true,
+                interactive: false,
             })
         );
     }
@@ -199,4 +749,209 @@ mod tests {
             .is_err()
         );
     }
+
+    #[test]
+    fn config_override() {
+        let path = std::env::temp_dir().join("cutergames_parse_config.toml");
+        std::fs::write(
+            &path,
+            "threads = 2\ngames = 10\n[[players]]\nname = \"A\"\npath = \"a\"\n",
+        )
+        .unwrap();
+
+        let settings =
+            parse(vec!["--config", path.to_str().unwrap(), "--threads", "4"].into_iter()).unwrap();
+        assert_eq!(settings.num_threads, 4);
+        assert_eq!(settings.num_games, Some(10));
+        assert_eq!(settings.players.len(), 1);
+
+        let settings = parse(
+            vec![
+                "--config",
+                path.to_str().unwrap(),
+                "--player",
+                "name=B",
+                "path=b",
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+        assert_eq!(settings.players.len(), 1);
+        assert_eq!(settings.players[0].name, "B");
+    }
+
+    #[test]
+    fn errors() {
+        let tests = [
+            (vec!["--unknown"], "Unknown parameter: --unknown"),
+            (
+                vec!["--games"],
+                "Missing parameter: --games expects a value",
+            ),
+            (
+                vec!["--games", "--threads", "4"],
+                "Missing parameter: --games expects a value",
+            ),
+            (
+                vec!["--threads", "four"],
+                "--threads expects a whole number, got \"four\"",
+            ),
+            (
+                vec!["--trinomial", "alpha=x"],
+                "--trinomial alpha expects a number, got \"x\"",
+            ),
+            (
+                vec!["--trinomial", "gamma=0.05"],
+                "Unknown parameter: --trinomial gamma",
+            ),
+            (
+                vec!["--player", "name=A", "colour=red"],
+                "Unknown parameter: --player colour",
+            ),
+            (
+                vec!["--player", "name=A"],
+                "Missing parameter: --player expects path=",
+            ),
+            (
+                vec!["--resume"],
+                "Missing parameter: --resume requires --state",
+            ),
+        ];
+
+        for (args, expected) in tests {
+            assert_eq!(parse(args.into_iter()).unwrap_err().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn validation() {
+        let executable = std::env::current_exe().unwrap();
+        let executable = executable.to_str().unwrap();
+        let player = |proto: &str, path: &str| PlayerSettings {
+            name: "A".to_string(),
+            path: path.to_string(),
+            proto: proto.to_string(),
+            ..Default::default()
+        };
+        let valid = TournamentSettings {
+            players: vec![player("ugi", executable), player("uai", executable)],
+            fens: vec!["startpos".to_string()],
+            sprt_trinomial: Some(SPRTSettings {
+                alpha: 0.05,
+                beta: 0.05,
+                elo0: 0.0,
+                elo1: 5.0,
+                autostop: true,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(validate(&valid), Ok(()));
+
+        let mut settings = valid.clone();
+        settings.players.pop();
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.players[0].proto = "test".to_string();
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.players[0].proto = "uci".to_string();
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.players[1].path = "/does/not/exist".to_string();
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.players[1].prefix = "/does/not/exist -n 5".to_string();
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.players[1].depth = Some(0);
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.players[1].tc = Some(ClockType::Time(1000, 0));
+        settings.players[1].movetime = Some(100);
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.players[1] = player("human", "");
+        settings.num_threads = 1;
+        assert_eq!(validate(&settings), Ok(()));
+        settings.num_threads = 2;
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.players[1] = player("builtin", "");
+        settings.players[1].name = "minimax".to_string();
+        assert_eq!(validate(&settings), Ok(()));
+        settings.players[1].name = "alphabeta".to_string();
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.fens.clear();
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.num_threads = 0;
+        assert!(validate(&settings).is_err());
+
+        for (alpha, beta, elo0, elo1) in [
+            (0.0, 0.05, 0.0, 5.0),
+            (0.05, 1.0, 0.0, 5.0),
+            (0.6, 0.6, 0.0, 5.0),
+            (0.05, 0.05, 5.0, 0.0),
+        ] {
+            let mut settings = valid.clone();
+            settings.sprt_trinomial = Some(SPRTSettings {
+                alpha,
+                beta,
+                elo0,
+                elo1,
+                autostop: true,
+            });
+            assert!(validate(&settings).is_err());
+        }
+
+        let tune = TuneSettings {
+            params: parse_spec("Hash, 16, 1, 64, 4, 0.002").unwrap(),
+            output: None,
+        };
+        let mut settings = valid.clone();
+        settings.tune = Some(tune);
+        assert!(validate(&settings).is_err());
+        settings.num_games = Some(100);
+        assert_eq!(validate(&settings), Ok(()));
+        settings.tune.as_mut().unwrap().params[0].start = 128.0;
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.http = Some("127.0.0.1:8080".to_string());
+        assert_eq!(validate(&settings), Ok(()));
+        settings.http = Some("localhost:8080".to_string());
+        assert_eq!(validate(&settings), Ok(()));
+        settings.http = Some("localhost".to_string());
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.num_threads = 0;
+        settings.listen = Some("0.0.0.0:9000".to_string());
+        assert_eq!(validate(&settings), Ok(()));
+        settings.listen = Some("9000".to_string());
+        assert_eq!(validate(&settings), Ok(()));
+        settings.listen = Some("coordinator".to_string());
+        assert!(validate(&settings).is_err());
+
+        let mut settings = valid.clone();
+        settings.num_threads = 1000;
+        assert!(validate(&settings).is_err());
+
+        let settings = TournamentSettings {
+            worker: Some("coordinator:9000".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(validate(&settings), Ok(()));
+    }
 }
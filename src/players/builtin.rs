@@ -0,0 +1,303 @@
+use crate::players::{
+    Player,
+    clock::{ClockType, Limits},
+};
+use ataxx::{moves::Move, position::Position, result::GameResult, side::Side};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Search depth for minimax when no depth= is given
+const DEFAULT_DEPTH: usize = 2;
+// Larger than any material difference on a 7x7 board
+const WIN_SCORE: i32 = 1000;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Strategy {
+    Random,
+    Greedy,
+    Minimax,
+}
+
+#[must_use]
+pub fn get_strategy(name: &str) -> Option<Strategy> {
+    match name.to_lowercase().as_str() {
+        "random" => Some(Strategy::Random),
+        "greedy" => Some(Strategy::Greedy),
+        "minimax" => Some(Strategy::Minimax),
+        _ => None,
+    }
+}
+
+// A reference player searching the Ataxx rules in-process
+pub struct BuiltinPlayer {
+    game: String,
+    pos: Position,
+    limits: Limits,
+    strategy: Option<Strategy>,
+    seed: u64,
+}
+
+impl BuiltinPlayer {
+    pub fn new(name: &str, game: &str, game_id: usize, limits: Limits) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default();
+
+        Self {
+            game: game.to_string(),
+            pos: Position::from_fen("startpos"),
+            limits,
+            strategy: get_strategy(name),
+            seed: (seed ^ game_id as u64) | 1,
+        }
+    }
+
+    // xorshift, good enough for picking moves
+    #[must_use]
+    fn pick(&mut self, len: usize) -> usize {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed % len as u64) as usize
+    }
+}
+
+// Pieces of the side to move minus the opponent's, read from the board part of the FEN
+#[must_use]
+fn material(fen: &str) -> i32 {
+    let mut words = fen.split_whitespace();
+    let board = words.next().unwrap_or_default();
+    let score = board.chars().fold(0, |score, c| match c {
+        'x' => score + 1,
+        'o' => score - 1,
+        _ => score,
+    });
+    if words.next() == Some("o") {
+        -score
+    } else {
+        score
+    }
+}
+
+// From the point of view of the side to move
+#[must_use]
+fn evaluate(pos: &Position) -> i32 {
+    if pos.is_gameover() {
+        return match (pos.get_result(), pos.turn) {
+            (Some(GameResult::BlackWin), Side::Black)
+            | (Some(GameResult::WhiteWin), Side::White) => WIN_SCORE,
+            (Some(GameResult::BlackWin), Side::White)
+            | (Some(GameResult::WhiteWin), Side::Black) => -WIN_SCORE,
+            _ => 0,
+        };
+    }
+    material(&pos.get_fen())
+}
+
+#[must_use]
+fn negamax(pos: &Position, depth: usize, mut alpha: i32, beta: i32) -> i32 {
+    if depth == 0 || pos.is_gameover() {
+        return evaluate(pos);
+    }
+
+    for mv in pos.legal_moves() {
+        let mut child = *pos;
+        child.makemove(&mv);
+        let score = -negamax(&child, depth - 1, -beta, -alpha);
+        if score >= beta {
+            return score;
+        }
+        alpha = alpha.max(score);
+    }
+    alpha
+}
+
+impl Player for BuiltinPlayer {
+    fn init(&mut self) -> bool {
+        // The moves come from the Ataxx rules
+        self.strategy.is_some() && self.game.eq_ignore_ascii_case("ataxx")
+    }
+
+    fn isready(&mut self) {}
+
+    fn set_option(&mut self, _name: &str, _value: &str) {}
+
+    fn set_position(&mut self, fen: &str) {
+        self.pos = Position::from_fen(fen);
+    }
+
+    fn get_move(&mut self, _clocks: &[ClockType; 2]) -> Option<String> {
+        let moves = self.pos.legal_moves();
+        if moves.is_empty() {
+            return None;
+        }
+
+        let depth = match self.strategy? {
+            Strategy::Random => 0,
+            Strategy::Greedy => 1,
+            Strategy::Minimax => self.limits.depth.unwrap_or(DEFAULT_DEPTH).max(1),
+        };
+        // Every move with the best score, picked between at random
+        let mut best = vec![];
+        let mut best_score = -WIN_SCORE - 1;
+        for mv in moves {
+            let score = if depth == 0 {
+                0
+            } else {
+                let mut child = self.pos;
+                child.makemove(&mv);
+                -negamax(&child, depth - 1, -WIN_SCORE - 1, WIN_SCORE + 1)
+            };
+            if score > best_score {
+                best_score = score;
+                best.clear();
+            }
+            if score == best_score {
+                best.push(mv);
+            }
+        }
+
+        let idx = self.pick(best.len());
+        Some(best[idx].to_string())
+    }
+
+    fn ponder(&mut self, _clocks: &[ClockType; 2]) {}
+
+    fn get_reported_time(&mut self) -> Option<u128> {
+        None
+    }
+
+    fn get_pid(&mut self) -> Option<u32> {
+        None
+    }
+
+    fn makemove(&mut self, mvstr: &str) -> bool {
+        let mv = Move::from_string(mvstr);
+        self.pos.makemove(&mv);
+        true
+    }
+
+    fn is_gameover(&mut self) -> bool {
+        self.pos.is_gameover()
+    }
+
+    fn is_legal(&mut self, mvstr: &str) -> bool {
+        let mv = Move::from_string(mvstr);
+        self.pos.is_legal_move(&mv)
+    }
+
+    fn get_turn(&mut self) -> Option<usize> {
+        match self.pos.turn {
+            Side::Black => Some(0),
+            Side::White => Some(1),
+        }
+    }
+
+    fn query_result(&mut self) -> Option<String> {
+        match self.pos.get_result() {
+            Some(GameResult::BlackWin) => Some("p1win".to_string()),
+            Some(GameResult::WhiteWin) => Some("p2win".to_string()),
+            Some(GameResult::Draw) => Some("draw".to_string()),
+            None => None,
+        }
+    }
+
+    fn get_stderr(&mut self) -> Vec<String> {
+        vec![]
+    }
+
+    fn get_clock(&mut self) -> &mut ClockType {
+        &mut self.limits.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tournament::PlayerSettings;
+
+    const CLOCKS: [ClockType; 2] = [ClockType::Infinite, ClockType::Infinite];
+
+    // x on c7 can take e5 with d6, but then loses three pieces to o on b4 jumping to d5. Jumping to a5 and
+    // taking b4 loses one
+    const TRAP: &str = "2x4/7/4o2/1o5/7/7/7 x 0 1";
+
+    #[must_use]
+    fn player(name: &str, depth: Option<usize>, fen: &str) -> BuiltinPlayer {
+        let mut player = BuiltinPlayer::new(name, "ataxx", 0, Limits::new(None, None, depth, None));
+        player.set_position(fen);
+        player
+    }
+
+    #[test]
+    fn search() {
+        let pos = Position::from_fen(TRAP);
+        let full = |depth| negamax(&pos, depth, -WIN_SCORE - 1, WIN_SCORE + 1);
+        assert_eq!(full(0), -1);
+        assert_eq!(full(1), 2);
+        assert_eq!(full(2), -1);
+
+        // Taking o's last piece wins, whoever is to move
+        let pos = Position::from_fen("7/7/7/7/7/1o5/x6 x 0 1");
+        assert_eq!(negamax(&pos, 1, -WIN_SCORE - 1, WIN_SCORE + 1), WIN_SCORE);
+        let pos = Position::from_fen("7/7/7/7/7/1o5/x6 o 0 1");
+        assert_eq!(negamax(&pos, 1, -WIN_SCORE - 1, WIN_SCORE + 1), WIN_SCORE);
+    }
+
+    #[test]
+    fn moves() {
+        assert_eq!(
+            player("greedy", None, TRAP).get_move(&CLOCKS),
+            Some("d6".to_string())
+        );
+        assert_eq!(
+            player("minimax", None, TRAP).get_move(&CLOCKS),
+            Some("c7a5".to_string())
+        );
+        assert_eq!(
+            player("minimax", Some(1), TRAP).get_move(&CLOCKS),
+            Some("d6".to_string())
+        );
+
+        for _ in 0..10 {
+            let mut random = player("random", None, "startpos");
+            let mv = random.get_move(&CLOCKS).unwrap();
+            assert!(random.is_legal(&mv), "{}", mv);
+        }
+
+        // Not a builtin player
+        assert_eq!(player("alphabeta", None, TRAP).get_move(&CLOCKS), None);
+    }
+
+    #[test]
+    fn untimed() {
+        // Searching to a depth takes as long as it takes, unless there's a clock
+        for name in ["random", "greedy", "minimax"] {
+            let mut settings = PlayerSettings {
+                name: name.to_string(),
+                proto: "builtin".to_string(),
+                ..Default::default()
+            };
+            assert_eq!(settings.limits().clock, ClockType::Infinite);
+            settings.movetime = Some(100);
+            assert_eq!(settings.limits().clock, ClockType::Movetime(100));
+        }
+    }
+
+    #[test]
+    fn strategies() {
+        assert_eq!(get_strategy("random"), Some(Strategy::Random));
+        assert_eq!(get_strategy("Greedy"), Some(Strategy::Greedy));
+        assert_eq!(get_strategy("MINIMAX"), Some(Strategy::Minimax));
+        assert_eq!(get_strategy(""), None);
+        assert_eq!(get_strategy("alphabeta"), None);
+    }
+
+    #[test]
+    fn materials() {
+        assert_eq!(material("x5o/7/7/7/7/7/o5x x 0 1"), 0);
+        assert_eq!(material("xx4o/7/7/7/7/7/o5x x 0 1"), 1);
+        assert_eq!(material("xx4o/7/7/7/7/7/o5x o 0 1"), -1);
+        assert_eq!(material("x5o/7/2-1-2/7/2-1-2/7/o5o x 0 1"), -2);
+    }
+}
@@ -1,5 +1,540 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ClockType {
     Time(u128, u128),
     Movetime(u128),
-    Depth(u8),
+    // The clock is topped up by base every moves moves, left counts down to the next time
+    Repeating {
+        moves: usize,
+        base: u128,
+        increment: u128,
+        remaining: u128,
+        left: usize,
+    },
+    // Simple delay, the clock only starts once the delay has passed
+    Delay(u128, u128),
+    // Bronstein delay, the time used is given back up to the delay
+    Bronstein(u128, u128),
+    // Once the main time runs out every move has a period, going over one uses it up
+    Byoyomi {
+        remaining: u128,
+        period: u128,
+        periods: usize,
+    },
+    // Searches bounded by depth or nodes alone
+    Infinite,
+}
+
+// What an engine is asked to search for each move
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    pub clock: ClockType,
+    pub depth: Option<usize>,
+    pub nodes: Option<usize>,
+    pub ponder: bool,
+}
+
+// Milliseconds as seconds without trailing zeros
+#[must_use]
+fn seconds(ms: u128) -> String {
+    format!("{}", ms as f64 / 1000.0)
+}
+
+#[must_use]
+fn parse_seconds(text: &str) -> Option<u128> {
+    text.parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(|secs| (secs * 1000.0).round() as u128)
+}
+
+// inf, 60, 60+0.5, 40/60+0.5, 60+delay0.5, 60+bronstein0.5 or 60+byoyomi5x3 with times in seconds
+pub fn parse_tc(text: &str) -> Result<ClockType, String> {
+    if text == "inf" {
+        return Ok(ClockType::Infinite);
+    }
+
+    let invalid = || {
+        format!(
+            "expected inf or a time control like 40/60+0.5, got \"{}\"",
+            text
+        )
+    };
+    let seconds = |text: &str| parse_seconds(text).ok_or_else(invalid);
+    let (moves, time) = match text.split_once('/') {
+        Some((moves, time)) => (
+            Some(
+                moves
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|moves| *moves > 0)
+                    .ok_or_else(invalid)?,
+            ),
+            time,
+        ),
+        None => (None, text),
+    };
+    let (base, extra) = match time.split_once('+') {
+        Some((base, extra)) => (seconds(base)?, Some(extra)),
+        None => (seconds(time)?, None),
+    };
+
+    let clock = match extra {
+        None => ClockType::Time(base, 0),
+        Some(extra) => {
+            if let Some(delay) = extra.strip_prefix("delay") {
+                ClockType::Delay(base, seconds(delay)?)
+            } else if let Some(delay) = extra.strip_prefix("bronstein") {
+                ClockType::Bronstein(base, seconds(delay)?)
+            } else if let Some(byoyomi) = extra.strip_prefix("byoyomi") {
+                let (period, periods) = match byoyomi.split_once('x') {
+                    Some((period, periods)) => (
+                        period,
+                        periods
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|periods| *periods > 0)
+                            .ok_or_else(invalid)?,
+                    ),
+                    None => (byoyomi, 1),
+                };
+                ClockType::Byoyomi {
+                    remaining: base,
+                    period: seconds(period)?,
+                    periods,
+                }
+            } else {
+                ClockType::Time(base, seconds(extra)?)
+            }
+        }
+    };
+
+    // Only byoyomi can do without main time
+    match (moves, clock) {
+        (_, ClockType::Byoyomi { period: 0, .. }) => Err(invalid()),
+        (None, clock @ ClockType::Byoyomi { .. }) => Ok(clock),
+        _ if base == 0 => Err(invalid()),
+        (Some(moves), ClockType::Time(base, increment)) => Ok(ClockType::Repeating {
+            moves,
+            base,
+            increment,
+            remaining: base,
+            left: moves,
+        }),
+        (Some(_), _) => Err(invalid()),
+        (None, clock) => Ok(clock),
+    }
+}
+
+impl ClockType {
+    // Charge a move to the clock, false if it took too long
+    #[must_use]
+    pub fn charge(&mut self, used: u128, margin: u128) -> bool {
+        match self {
+            ClockType::Movetime(ms) => used <= *ms + margin,
+            ClockType::Infinite => true,
+            ClockType::Time(remaining, increment) => {
+                if used > *remaining + margin {
+                    return false;
+                }
+                *remaining = remaining.saturating_sub(used) + *increment;
+                true
+            }
+            ClockType::Repeating {
+                moves,
+                base,
+                increment,
+                remaining,
+                left,
+            } => {
+                if used > *remaining + margin {
+                    return false;
+                }
+                *remaining = remaining.saturating_sub(used) + *increment;
+                *left -= 1;
+                if *left == 0 {
+                    *remaining += *base;
+                    *left = *moves;
+                }
+                true
+            }
+            ClockType::Delay(remaining, delay) => {
+                let used = used.saturating_sub(*delay);
+                if used > *remaining + margin {
+                    return false;
+                }
+                *remaining = remaining.saturating_sub(used);
+                true
+            }
+            ClockType::Bronstein(remaining, delay) => {
+                if used > *remaining + margin {
+                    return false;
+                }
+                *remaining = remaining.saturating_sub(used) + used.min(*delay);
+                true
+            }
+            ClockType::Byoyomi {
+                remaining,
+                period,
+                periods,
+            } => {
+                let mut over = used.saturating_sub(*remaining);
+                *remaining = remaining.saturating_sub(used);
+                while over > *period && *periods > 1 {
+                    over -= *period;
+                    *periods -= 1;
+                }
+                over <= *period + margin
+            }
+        }
+    }
+
+    // The longest the next move can take without losing on time, leaving out the margin
+    #[must_use]
+    pub fn allowance(&self) -> Option<u128> {
+        match self {
+            ClockType::Movetime(ms) => Some(*ms),
+            ClockType::Infinite => None,
+            ClockType::Time(remaining, _)
+            | ClockType::Repeating { remaining, .. }
+            | ClockType::Bronstein(remaining, _) => Some(*remaining),
+            ClockType::Delay(remaining, delay) => Some(remaining + delay),
+            ClockType::Byoyomi {
+                remaining,
+                period,
+                periods,
+            } => Some(remaining + period * *periods as u128),
+        }
+    }
+
+    // The time left and what's added each move. Delays and byoyomi periods are never banked like an increment,
+    // so there's none for them, but the next byoyomi period is always there to use
+    #[must_use]
+    pub fn time_left(&self) -> Option<(u128, Option<u128>)> {
+        match self {
+            ClockType::Time(remaining, increment)
+            | ClockType::Repeating {
+                remaining,
+                increment,
+                ..
+            }
+            | ClockType::Bronstein(remaining, increment) => Some((*remaining, Some(*increment))),
+            ClockType::Delay(remaining, _) => Some((*remaining, None)),
+            ClockType::Byoyomi {
+                remaining, period, ..
+            } => Some((remaining + period, None)),
+            ClockType::Movetime(_) | ClockType::Infinite => None,
+        }
+    }
+}
+
+impl Limits {
+    // A tc or movetime alongside depth or nodes is a safety net, without any the default is 10ms a move
+    #[must_use]
+    pub fn new(
+        tc: Option<ClockType>,
+        movetime: Option<usize>,
+        depth: Option<usize>,
+        nodes: Option<usize>,
+    ) -> Self {
+        let clock = match (tc, movetime) {
+            (Some(tc), _) => tc,
+            (None, Some(ms)) => ClockType::Movetime(ms as u128),
+            (None, None) if depth.is_some() || nodes.is_some() => ClockType::Infinite,
+            (None, None) => ClockType::Movetime(10),
+        };
+        Self {
+            clock,
+            depth,
+            nodes,
+            ponder: false,
+        }
+    }
+
+    // go p1time 60000 p2time 58000 p1inc 500 p2inc 500 movestogo 12 depth 8, sides names the clocks
+    #[must_use]
+    pub fn go_command(&self, clocks: &[ClockType; 2], sides: [&str; 2]) -> String {
+        let mut go = "go".to_string();
+        match self.clock {
+            ClockType::Movetime(ms) => go += &format!(" movetime {}", ms),
+            ClockType::Infinite => {}
+            _ => {
+                let times = sides
+                    .iter()
+                    .zip(clocks.iter().map(ClockType::time_left))
+                    .collect::<Vec<_>>();
+                for (side, time) in &times {
+                    if let Some((remaining, _)) = time {
+                        go += &format!(" {}time {}", side, remaining);
+                    }
+                }
+                for (side, time) in &times {
+                    if let Some((_, Some(increment))) = time {
+                        go += &format!(" {}inc {}", side, increment);
+                    }
+                }
+            }
+        }
+        if let ClockType::Repeating { left, .. } = self.clock {
+            go += &format!(" movestogo {}", left);
+        }
+        if let Some(depth) = self.depth {
+            go += &format!(" depth {}", depth);
+        }
+        if let Some(nodes) = self.nodes {
+            go += &format!(" nodes {}", nodes);
+        }
+        go + "\n"
+    }
+
+    // For PGN TimeControl tags, 40/60+0.5 or 0.1/move, followed by any depth or nodes limit
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let time = |base, increment| match increment {
+            0 => seconds(base),
+            _ => format!("{}+{}", seconds(base), seconds(increment)),
+        };
+        let mut text = match self.clock {
+            ClockType::Time(base, increment) => time(base, increment),
+            ClockType::Repeating {
+                moves,
+                base,
+                increment,
+                ..
+            } => format!("{}/{}", moves, time(base, increment)),
+            ClockType::Delay(base, delay) => format!("{}+delay{}", seconds(base), seconds(delay)),
+            ClockType::Bronstein(base, delay) => {
+                format!("{}+bronstein{}", seconds(base), seconds(delay))
+            }
+            ClockType::Byoyomi {
+                remaining,
+                period,
+                periods,
+            } => format!(
+                "{}+byoyomi{}x{}",
+                seconds(remaining),
+                seconds(period),
+                periods
+            ),
+            ClockType::Movetime(ms) => format!("{}/move", seconds(ms)),
+            ClockType::Infinite => "inf".to_string(),
+        };
+        if let Some(depth) = self.depth {
+            text += &format!(" depth={}", depth);
+        }
+        if let Some(nodes) = self.nodes {
+            text += &format!(" nodes={}", nodes);
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits() {
+        let clocks = [ClockType::Movetime(10), ClockType::Movetime(10)];
+
+        let default = Limits::new(None, None, None, None);
+        assert_eq!(default.clock, ClockType::Movetime(10));
+        assert_eq!(
+            default.go_command(&clocks, ["p1", "p2"]),
+            "go movetime 10\n"
+        );
+        assert_eq!(default.describe(), "0.01/move");
+
+        let depth = Limits::new(None, None, Some(8), None);
+        assert_eq!(depth.clock, ClockType::Infinite);
+        assert_eq!(depth.go_command(&clocks, ["p1", "p2"]), "go depth 8\n");
+        assert_eq!(depth.describe(), "inf depth=8");
+
+        let nodes = Limits::new(None, Some(1000), None, Some(5000));
+        assert_eq!(
+            nodes.go_command(&clocks, ["p1", "p2"]),
+            "go movetime 1000 nodes 5000\n"
+        );
+        assert_eq!(nodes.describe(), "1/move nodes=5000");
+
+        let time = Limits::new(Some(ClockType::Time(60000, 500)), None, None, None);
+        assert_eq!(time.describe(), "60+0.5");
+    }
+
+    #[test]
+    fn tc() {
+        assert_eq!(parse_tc("inf"), Ok(ClockType::Infinite));
+        assert_eq!(parse_tc("60"), Ok(ClockType::Time(60000, 0)));
+        assert_eq!(parse_tc("8+0.08"), Ok(ClockType::Time(8000, 80)));
+        assert_eq!(
+            parse_tc("40/60+0.5"),
+            Ok(ClockType::Repeating {
+                moves: 40,
+                base: 60000,
+                increment: 500,
+                remaining: 60000,
+                left: 40,
+            })
+        );
+        for text in [
+            "",
+            "0",
+            "x",
+            "60+",
+            "-1+1",
+            "0/60",
+            "40/",
+            "40/60+x",
+            "40/60+delay1",
+            "0+delay1",
+            "60+delay",
+            "60+byoyomi0",
+            "60+byoyomi5x0",
+        ] {
+            assert!(parse_tc(text).is_err(), "{}", text);
+        }
+
+        let limits = Limits::new(Some(parse_tc("40/60+0.5").unwrap()), None, None, None);
+        assert_eq!(limits.describe(), "40/60+0.5");
+    }
+
+    #[test]
+    fn repeating() {
+        let mut clock = parse_tc("2/10").unwrap();
+        let other = ClockType::Time(5000, 100);
+        let limits = Limits::new(Some(clock.clone()), None, None, None);
+        assert_eq!(
+            limits.go_command(&[clock.clone(), other.clone()], ["b", "w"]),
+            "go btime 10000 wtime 5000 binc 0 winc 100 movestogo 2\n"
+        );
+
+        // The clock is topped up once the period's moves are played
+        assert!(clock.charge(4000, 10));
+        assert!(matches!(
+            clock,
+            ClockType::Repeating {
+                remaining: 6000,
+                left: 1,
+                ..
+            }
+        ));
+        assert!(clock.charge(5000, 10));
+        assert!(matches!(
+            clock,
+            ClockType::Repeating {
+                remaining: 11000,
+                left: 2,
+                ..
+            }
+        ));
+        assert!(!clock.charge(11011, 10));
+
+        // Delays and byoyomi periods aren't increments
+        let delay = parse_tc("60+delay0.5").unwrap();
+        let byoyomi = parse_tc("60+byoyomi5x3").unwrap();
+        let limits = Limits::new(Some(delay.clone()), None, None, None);
+        assert_eq!(
+            limits.go_command(&[delay, byoyomi], ["b", "w"]),
+            "go btime 60000 wtime 65000\n"
+        );
+
+        let mut clock = ClockType::Time(1000, 100);
+        assert!(clock.charge(1005, 10));
+        assert_eq!(clock, ClockType::Time(100, 100));
+
+        let mut clock = ClockType::Movetime(100);
+        assert!(clock.charge(110, 10));
+        assert!(!clock.charge(111, 10));
+    }
+
+    #[test]
+    fn delays() {
+        assert_eq!(parse_tc("60+delay2"), Ok(ClockType::Delay(60000, 2000)));
+        assert_eq!(
+            parse_tc("60+bronstein2"),
+            Ok(ClockType::Bronstein(60000, 2000))
+        );
+        assert_eq!(
+            parse_tc("0+byoyomi10x3"),
+            Ok(ClockType::Byoyomi {
+                remaining: 0,
+                period: 10000,
+                periods: 3
+            })
+        );
+        for text in [
+            "60+delay2",
+            "60+bronstein2",
+            "60+byoyomi5x3",
+            "0+byoyomi10x1",
+        ] {
+            let limits = Limits::new(Some(parse_tc(text).unwrap()), None, None, None);
+            assert_eq!(limits.describe(), text);
+        }
+
+        // The delay is free, anything over it comes off the clock
+        let mut clock = ClockType::Delay(1000, 200);
+        assert!(clock.charge(150, 10));
+        assert_eq!(clock, ClockType::Delay(1000, 200));
+        assert!(clock.charge(700, 10));
+        assert_eq!(clock, ClockType::Delay(500, 200));
+        assert!(!clock.charge(711, 10));
+
+        // The time used is given back, up to the delay
+        let mut clock = ClockType::Bronstein(1000, 200);
+        assert!(clock.charge(150, 10));
+        assert_eq!(clock, ClockType::Bronstein(1000, 200));
+        assert!(clock.charge(700, 10));
+        assert_eq!(clock, ClockType::Bronstein(500, 200));
+        assert!(!clock.charge(511, 10));
+
+        let mut clock = parse_tc("1+byoyomi0.5x3").unwrap();
+        let other = ClockType::Bronstein(2000, 100);
+        let limits = Limits::new(Some(clock.clone()), None, None, None);
+        assert_eq!(
+            limits.go_command(&[clock.clone(), other], ["p1", "p2"]),
+            "go p1time 1500 p2time 2000 p2inc 100\n"
+        );
+        assert!(clock.charge(800, 10));
+        // Into the byoyomi but within a period
+        assert!(clock.charge(600, 10));
+        assert_eq!(
+            clock,
+            ClockType::Byoyomi {
+                remaining: 0,
+                period: 500,
+                periods: 3
+            }
+        );
+        // Two periods used up
+        assert!(clock.charge(1200, 10));
+        assert_eq!(
+            clock,
+            ClockType::Byoyomi {
+                remaining: 0,
+                period: 500,
+                periods: 1
+            }
+        );
+        assert!(clock.charge(510, 10));
+        assert!(!clock.charge(511, 10));
+    }
+
+    #[test]
+    fn allowances() {
+        assert_eq!(ClockType::Movetime(100).allowance(), Some(100));
+        assert_eq!(ClockType::Infinite.allowance(), None);
+        assert_eq!(ClockType::Time(1000, 100).allowance(), Some(1000));
+        assert_eq!(ClockType::Delay(1000, 200).allowance(), Some(1200));
+        assert_eq!(ClockType::Bronstein(1000, 200).allowance(), Some(1000));
+
+        // Every move up to the allowance is in time
+        for text in ["1+delay0.5", "1+bronstein0.5", "1+byoyomi0.5x3", "40/1+0.5"] {
+            let clock = parse_tc(text).unwrap();
+            let allowance = clock.allowance().unwrap();
+            assert!(clock.clone().charge(allowance, 0), "{}", text);
+            assert!(!clock.clone().charge(allowance + 1, 0), "{}", text);
+        }
+    }
 }
@@ -0,0 +1,81 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+// Sent to the engine
+pub const SENT: char = '>';
+// Received from the engine on stdout
+pub const RECEIVED: char = '<';
+// Received from the engine on stderr
+pub const STDERR: char = '!';
+
+pub struct CommLog {
+    file: BufWriter<File>,
+    start: Instant,
+}
+
+impl CommLog {
+    pub fn new(path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            file: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+        })
+    }
+
+    pub fn write(&mut self, direction: char, msg: &str) {
+        let elapsed = self.start.elapsed().as_secs_f32();
+        // Flushed every line so the log survives an engine taking the process down with it
+        let _ = writeln!(
+            self.file,
+            "[{:>10.3}] {} {}",
+            elapsed,
+            direction,
+            msg.trim_end()
+        )
+        .and_then(|_| self.file.flush());
+    }
+}
+
+// Engine names can contain anything, file names can't
+#[must_use]
+pub fn file_name(game_id: usize, slot: usize, name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("game{}-p{}-{}.log", game_id, slot + 1, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::read_to_string;
+
+    #[test]
+    fn success() {
+        assert_eq!(file_name(12, 0, "Engine 1.0"), "game12-p1-Engine_1.0.log");
+        assert_eq!(file_name(3, 1, "a/b\\c"), "game3-p2-a_b_c.log");
+
+        let path = std::env::temp_dir().join("cutergames_commlog_success.log");
+        let mut log = CommLog::new(&path).unwrap();
+        log.write(SENT, "isready\n");
+        log.write(RECEIVED, "readyok\n");
+        log.write(STDERR, "warning");
+
+        let text = read_to_string(&path).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with("] > isready"));
+        assert!(lines[1].ends_with("] < readyok"));
+        assert!(lines[2].ends_with("] ! warning"));
+    }
+}
@@ -0,0 +1,297 @@
+use crate::players::{
+    Player,
+    clock::{ClockType, Limits},
+};
+use ataxx::{moves::Move, position::Position, result::GameResult, side::Side};
+use std::{
+    io::Write,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{Receiver, RecvTimeoutError, channel},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// How often a player waiting for a move checks whether it's run out of time or the games were aborted
+const POLL_TIME: Duration = Duration::from_millis(100);
+
+// Lines typed into the terminal. They're read on a thread of their own that outlives any one move, so a
+// player can stop waiting without losing the next line
+static INPUT: OnceLock<Mutex<Receiver<String>>> = OnceLock::new();
+
+// Counts aborts, players waiting for a move give up when it changes
+static ABORTS: AtomicUsize = AtomicUsize::new(0);
+
+// A person typing moves into the terminal, with the rules checked by the Ataxx judge
+pub struct HumanPlayer {
+    name: String,
+    game: String,
+    pos: Position,
+    limits: Limits,
+}
+
+impl HumanPlayer {
+    pub fn new(name: &str, game: &str, limits: Limits) -> Self {
+        Self {
+            name: name.to_string(),
+            game: game.to_string(),
+            pos: Position::from_fen("startpos"),
+            limits,
+        }
+    }
+}
+
+pub fn abort_all() {
+    ABORTS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[must_use]
+fn input() -> &'static Mutex<Receiver<String>> {
+    INPUT.get_or_init(|| {
+        let (send, recv) = channel();
+        thread::spawn(move || {
+            loop {
+                let mut buffer = String::new();
+                match std::io::stdin().read_line(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        if send.send(buffer).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+        Mutex::new(recv)
+    })
+}
+
+// The next line typed, or None once the deadline passes, the games are aborted or the input ends
+#[must_use]
+fn wait_line(input: &Receiver<String>, deadline: Option<Instant>, aborts: usize) -> Option<String> {
+    loop {
+        if ABORTS.load(Ordering::Relaxed) != aborts {
+            return None;
+        }
+        let wait = match deadline {
+            Some(deadline) => deadline
+                .checked_duration_since(Instant::now())
+                .filter(|left| !left.is_zero())?
+                .min(POLL_TIME),
+            None => POLL_TIME,
+        };
+        match input.recv_timeout(wait) {
+            Ok(line) => return Some(line),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+// 0000, a1 or a1c3, checked before the judge sees it
+#[must_use]
+fn is_move_text(text: &str) -> bool {
+    let is_square =
+        |square: &[u8]| (b'a'..=b'g').contains(&square[0]) && (b'1'..=b'7').contains(&square[1]);
+    match text.as_bytes() {
+        b"0000" => true,
+        bytes @ [_, _] => is_square(bytes),
+        bytes @ [_, _, _, _] => is_square(&bytes[..2]) && is_square(&bytes[2..]),
+        _ => false,
+    }
+}
+
+// x5o/7/7/7/7/7/o5x x 0 1 drawn with the rank and file names around it
+#[must_use]
+fn render(fen: &str) -> String {
+    let files = "  a b c d e f g\n";
+    let mut board = files.to_string();
+    let ranks = fen.split_whitespace().next().unwrap_or_default().split('/');
+    for (idx, rank) in ranks.enumerate() {
+        let number = 7 - idx;
+        board += &format!("{} ", number);
+        for c in rank.chars() {
+            match c.to_digit(10) {
+                Some(gap) => board += &". ".repeat(gap as usize),
+                None => board += &format!("{} ", c),
+            }
+        }
+        board += &format!("{}\n", number);
+    }
+    board + files
+}
+
+#[must_use]
+fn clock_text(clock: &ClockType) -> Option<String> {
+    let seconds = |ms: u128| format!("{:.1}s", ms as f64 / 1000.0);
+    match clock {
+        ClockType::Movetime(ms) => Some(format!("{} for this move", seconds(*ms))),
+        ClockType::Infinite => None,
+        clock => clock
+            .time_left()
+            .map(|(remaining, _)| format!("{} left", seconds(remaining))),
+    }
+}
+
+impl Player for HumanPlayer {
+    fn init(&mut self) -> bool {
+        // Moves are checked with the Ataxx rules
+        self.game.eq_ignore_ascii_case("ataxx")
+    }
+
+    fn isready(&mut self) {}
+
+    fn set_option(&mut self, _name: &str, _value: &str) {}
+
+    fn set_position(&mut self, fen: &str) {
+        self.pos = Position::from_fen(fen);
+    }
+
+    fn get_move(&mut self, clocks: &[ClockType; 2]) -> Option<String> {
+        let aborts = ABORTS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let deadline = self
+            .limits
+            .clock
+            .allowance()
+            .map(|allowance| start + Duration::from_millis(allowance as u64));
+
+        println!();
+        print!("{}", render(&self.pos.get_fen()));
+        for (side, clock) in ["x", "o"].iter().zip(clocks) {
+            if let Some(text) = clock_text(clock) {
+                println!("{}: {}", side, text);
+            }
+        }
+
+        loop {
+            print!("{} to move: ", self.name);
+            let _ = std::io::stdout().flush();
+
+            let line = wait_line(&input().lock().expect("Lock fail thingy"), deadline, aborts);
+            let Some(buffer) = line else {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    println!();
+                    println!("{} ran out of time", self.name);
+                }
+                return None;
+            };
+
+            let mvstr = buffer.trim();
+            if is_move_text(mvstr) && self.is_legal(mvstr) {
+                return Some(mvstr.to_string());
+            }
+            println!("Illegal move \"{}\", try again", mvstr);
+        }
+    }
+
+    fn ponder(&mut self, _clocks: &[ClockType; 2]) {}
+
+    fn get_reported_time(&mut self) -> Option<u128> {
+        None
+    }
+
+    fn get_pid(&mut self) -> Option<u32> {
+        None
+    }
+
+    fn makemove(&mut self, mvstr: &str) -> bool {
+        let mv = Move::from_string(mvstr);
+        self.pos.makemove(&mv);
+        true
+    }
+
+    fn is_gameover(&mut self) -> bool {
+        self.pos.is_gameover()
+    }
+
+    fn is_legal(&mut self, mvstr: &str) -> bool {
+        let mv = Move::from_string(mvstr);
+        self.pos.is_legal_move(&mv)
+    }
+
+    fn get_turn(&mut self) -> Option<usize> {
+        match self.pos.turn {
+            Side::Black => Some(0),
+            Side::White => Some(1),
+        }
+    }
+
+    fn query_result(&mut self) -> Option<String> {
+        match self.pos.get_result() {
+            Some(GameResult::BlackWin) => Some("p1win".to_string()),
+            Some(GameResult::WhiteWin) => Some("p2win".to_string()),
+            Some(GameResult::Draw) => Some("draw".to_string()),
+            None => None,
+        }
+    }
+
+    fn get_stderr(&mut self) -> Vec<String> {
+        vec![]
+    }
+
+    fn get_clock(&mut self) -> &mut ClockType {
+        &mut self.limits.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves() {
+        for text in ["0000", "a1", "g7", "a1c3"] {
+            assert!(is_move_text(text), "{}", text);
+        }
+        for text in ["", "a", "a0", "h1", "a8", "a1c", "a1c3e5", "A1"] {
+            assert!(!is_move_text(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn waiting() {
+        let (send, recv) = channel();
+        let aborts = ABORTS.load(Ordering::Relaxed);
+
+        send.send("a1\n".to_string()).unwrap();
+        assert_eq!(wait_line(&recv, None, aborts), Some("a1\n".to_string()));
+
+        // Out of time
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(50);
+        assert_eq!(wait_line(&recv, Some(deadline), aborts), None);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(start.elapsed() < POLL_TIME * 2);
+
+        // Aborted, whether or not there's a line waiting
+        abort_all();
+        assert_eq!(wait_line(&recv, None, aborts), None);
+        send.send("a1\n".to_string()).unwrap();
+        assert_eq!(wait_line(&recv, None, aborts), None);
+
+        // The input ended
+        drop(send);
+        let aborts = ABORTS.load(Ordering::Relaxed);
+        assert_eq!(wait_line(&recv, None, aborts), Some("a1\n".to_string()));
+        assert_eq!(wait_line(&recv, None, aborts), None);
+    }
+
+    #[test]
+    fn board() {
+        assert_eq!(
+            render("x5o/7/2-1-2/7/2-1-2/7/o5x x 0 1"),
+            "  a b c d e f g\n\
+             7 x . . . . . o 7\n\
+             6 . . . . . . . 6\n\
+             5 . . - . - . . 5\n\
+             4 . . . . . . . 4\n\
+             3 . . - . - . . 3\n\
+             2 . . . . . . . 2\n\
+             1 o . . . . . x 1\n  \
+             a b c d e f g\n"
+        );
+    }
+}
@@ -1,6 +1,21 @@
-use crate::players::{clock::ClockType, uai::UAIEngine, uci::UCIEngine, ugi::UGIEngine};
-
+use crate::players::{
+    builtin::BuiltinPlayer,
+    clock::{ClockType, Limits},
+    commlog::CommLog,
+    human::HumanPlayer,
+    uai::UAIEngine,
+    uci::UCIEngine,
+    ugi::UGIEngine,
+};
+use std::path::Path;
+
+pub use process::EngineCommand;
+
+pub mod builtin;
 pub mod clock;
+pub mod commlog;
+mod human;
+mod ponder;
 mod process;
 pub mod uai;
 pub mod uci;
@@ -11,38 +26,132 @@ pub enum Protocol {
     UGI,
     UAI,
     UCI,
+    Human,
+    Builtin,
 }
 
 #[must_use]
-pub fn create(name: &str, path: &str, proto: Protocol, debug: bool) -> Box<dyn Player> {
+#[allow(clippy::too_many_arguments)]
+pub fn create(
+    name: &str,
+    command: &EngineCommand,
+    proto: Protocol,
+    limits: Limits,
+    game: &str,
+    game_id: usize,
+    debug: bool,
+    log_path: Option<&Path>,
+) -> Box<dyn Player> {
+    // A player without its log is still better than no game at all
+    let log = log_path.and_then(|log_path| match CommLog::new(log_path) {
+        Ok(log) => Some(log),
+        Err(err) => {
+            println!(
+                "<Error> Failed to create log {}: {}",
+                log_path.display(),
+                err
+            );
+            None
+        }
+    });
+
     match proto {
-        Protocol::UGI => Box::new(UGIEngine::new(name, path, ClockType::Movetime(10), debug)),
-        Protocol::UAI => Box::new(UAIEngine::new(name, path, ClockType::Movetime(10), debug)),
-        Protocol::UCI => Box::new(UCIEngine::new(name, path, debug)),
+        Protocol::UGI => Box::new(UGIEngine::new(
+            name, command, game, game_id, limits, debug, log,
+        )),
+        Protocol::UAI => Box::new(UAIEngine::new(
+            name, command, game, game_id, limits, debug, log,
+        )),
+        Protocol::UCI => Box::new(UCIEngine::new(name, command, game, game_id, debug, log)),
+        Protocol::Human => Box::new(HumanPlayer::new(name, game, limits)),
+        Protocol::Builtin => Box::new(BuiltinPlayer::new(name, game, game_id, limits)),
     }
 }
 
+pub fn kill_all() {
+    process::kill_all();
+    // People at the terminal can't be killed, they give up waiting instead
+    human::abort_all();
+}
+
+pub fn kill(pid: u32) {
+    process::kill(pid);
+}
+
 #[must_use]
 pub fn get_protocol(name: &str) -> Option<Protocol> {
     match name.to_lowercase().as_str() {
         "ugi" => Some(Protocol::UGI),
         "uai" => Some(Protocol::UAI),
         "uci" => Some(Protocol::UCI),
+        "human" => Some(Protocol::Human),
+        "builtin" => Some(Protocol::Builtin),
+        _ => None,
+    }
+}
+
+// bestmove a1 ponder b2
+#[must_use]
+pub fn parse_bestmove(line: &str) -> Option<(String, Option<String>)> {
+    let mut words = line.split_whitespace();
+    if words.next() != Some("bestmove") {
+        return None;
+    }
+    let mv = words.next().unwrap_or_default().to_string();
+    let ponder = match (words.next(), words.next()) {
+        (Some("ponder"), Some(ponder)) => Some(ponder.to_string()),
         _ => None,
+    };
+    Some((mv, ponder))
+}
+
+// info depth 5 time 120 nodes 1000
+#[must_use]
+pub fn parse_info_time(line: &str) -> Option<u128> {
+    let mut words = line.split_whitespace();
+    if words.next() != Some("info") {
+        return None;
+    }
+    while let Some(word) = words.next() {
+        match word {
+            // Everything after string is free text
+            "string" => return None,
+            "time" => return words.next()?.parse().ok(),
+            _ => {}
+        }
     }
+    None
 }
 
 pub trait Player {
-    fn init(&mut self);
+    #[must_use]
+    fn init(&mut self) -> bool;
 
     fn isready(&mut self);
 
+    fn set_option(&mut self, name: &str, value: &str);
+
     fn set_position(&mut self, fen: &str);
 
-    fn get_move(&mut self) -> Option<String>;
+    // Both sides' clocks, by turn
+    fn get_move(&mut self, clocks: &[ClockType; 2]) -> Option<String>;
+
+    // Think on the opponent's time, if the engine said what it expects them to play
+    fn ponder(&mut self, clocks: &[ClockType; 2]);
+
+    // The time reported in the last info line of the previous search
+    #[must_use]
+    fn get_reported_time(&mut self) -> Option<u128>;
+
+    // The process to kill if the player stalls, none for players that run in-process
+    #[must_use]
+    fn get_pid(&mut self) -> Option<u32>;
 
     fn makemove(&mut self, mvstr: &str) -> bool;
 
+    #[must_use]
+    fn get_stderr(&mut self) -> Vec<String>;
+
     #[must_use]
     fn get_clock(&mut self) -> &mut ClockType;
 
@@ -76,6 +185,37 @@ mod tests {
         assert_eq!(get_protocol("uci"), Some(Protocol::UCI));
         assert_eq!(get_protocol("Uci"), Some(Protocol::UCI));
         assert_eq!(get_protocol("UCI"), Some(Protocol::UCI));
+
+        assert_eq!(get_protocol("human"), Some(Protocol::Human));
+        assert_eq!(get_protocol("Builtin"), Some(Protocol::Builtin));
+    }
+
+    #[test]
+    fn bestmove() {
+        assert_eq!(
+            parse_bestmove("bestmove a1\n"),
+            Some(("a1".to_string(), None))
+        );
+        assert_eq!(
+            parse_bestmove("bestmove a1 ponder b2\n"),
+            Some(("a1".to_string(), Some("b2".to_string())))
+        );
+        assert_eq!(parse_bestmove("bestmove\n"), Some((String::new(), None)));
+        assert_eq!(parse_bestmove("best\n"), None);
+        assert_eq!(parse_bestmove("info bestmove a1\n"), None);
+    }
+
+    #[test]
+    fn info_time() {
+        assert_eq!(
+            parse_info_time("info depth 5 time 120 nodes 1000\n"),
+            Some(120)
+        );
+        assert_eq!(parse_info_time("info time 0\n"), Some(0));
+        assert_eq!(parse_info_time("info depth 5 nodes 1000\n"), None);
+        assert_eq!(parse_info_time("info string time 5\n"), None);
+        assert_eq!(parse_info_time("info time x\n"), None);
+        assert_eq!(parse_info_time("bestmove a1 time 5\n"), None);
     }
 
     #[test]
@@ -0,0 +1,229 @@
+use crate::players::{parse_bestmove, process::EngineProcess};
+
+// What pondering needs from the engine
+pub trait Engine {
+    fn send(&mut self, msg: &str);
+    // Wait for the bestmove ending the search
+    fn wait_bestmove(&mut self);
+}
+
+impl Engine for EngineProcess {
+    fn send(&mut self, msg: &str) {
+        EngineProcess::send(self, msg);
+    }
+
+    fn wait_bestmove(&mut self) {
+        self.wait_magic(&mut |msg: &str| -> bool { parse_bestmove(msg).is_some() });
+    }
+}
+
+// Thinking on the opponent's time for the protocols with go ponder
+#[derive(Default)]
+pub struct Ponder {
+    is_enabled: bool,
+    fen: String,
+    history: Vec<String>,
+    // The reply expected by the engine's last bestmove
+    next: Option<String>,
+    // The reply being pondered on
+    expected: Option<String>,
+    is_hit: bool,
+}
+
+#[must_use]
+pub fn position_command(fen: &str) -> String {
+    if fen == "startpos" {
+        "position startpos\n".to_string()
+    } else {
+        format!("position fen {}\n", fen)
+    }
+}
+
+impl Ponder {
+    #[must_use]
+    pub fn new(is_enabled: bool) -> Self {
+        Self {
+            is_enabled,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_position(&mut self, fen: &str) {
+        self.fen = fen.to_string();
+        self.history.clear();
+        self.next = None;
+        self.expected = None;
+        self.is_hit = false;
+    }
+
+    // Remember what the engine expects the opponent to play
+    pub fn finish(&mut self, ponder: Option<String>) {
+        self.next = ponder.filter(|_| self.is_enabled);
+    }
+
+    // Think about the expected reply while the opponent is thinking
+    pub fn start(&mut self, engine: &mut impl Engine, go: &str) {
+        let Some(mv) = self.next.take() else {
+            return;
+        };
+        engine.send(&format!("moves {}\n", mv));
+        engine.send(&go.replacen("go", "go ponder", 1));
+        self.expected = Some(mv);
+    }
+
+    // Start searching, carrying on from the ponder search if it guessed right
+    pub fn go(&mut self, engine: &mut impl Engine, go: &str) {
+        if self.is_hit {
+            self.is_hit = false;
+            engine.send("ponderhit\n");
+        } else {
+            engine.send(go);
+        }
+    }
+
+    pub fn makemove(&mut self, engine: &mut impl Engine, mvstr: &str) {
+        self.history.push(mvstr.to_string());
+        match self.expected.take() {
+            // The engine already has the move, ponderhit is sent when it's asked to move
+            Some(expected) if expected == mvstr => self.is_hit = true,
+            // The engine's position is a move ahead, stop and start again from the real one
+            Some(_) => self.restart(engine),
+            None => engine.send(&format!("moves {}\n", mvstr)),
+        }
+    }
+
+    // Engines don't answer questions mid-search, so any ponder search is stopped first and the next go starts afresh
+    pub fn settle(&mut self, engine: &mut impl Engine) {
+        if self.is_hit {
+            self.is_hit = false;
+            engine.send("stop\n");
+            engine.wait_bestmove();
+        } else if self.expected.take().is_some() {
+            self.restart(engine);
+        }
+    }
+
+    fn restart(&mut self, engine: &mut impl Engine) {
+        engine.send("stop\n");
+        engine.wait_bestmove();
+        engine.send(&position_command(&self.fen));
+        for mv in &self.history {
+            engine.send(&format!("moves {}\n", mv));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Everything sent, with waits for a bestmove as "wait"
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl Engine for Recorder {
+        fn send(&mut self, msg: &str) {
+            self.0.push(msg.trim_end().to_string());
+        }
+
+        fn wait_bestmove(&mut self) {
+            self.0.push("wait".to_string());
+        }
+    }
+
+    // A game from the start where the engine played a1 and expects b1
+    #[must_use]
+    fn pondering(is_enabled: bool) -> (Ponder, Recorder) {
+        let mut ponder = Ponder::new(is_enabled);
+        let mut engine = Recorder::default();
+        ponder.set_position("startpos");
+        ponder.makemove(&mut engine, "a1");
+        ponder.finish(Some("b1".to_string()));
+        ponder.start(&mut engine, "go movetime 100\n");
+        (ponder, engine)
+    }
+
+    #[test]
+    fn disabled() {
+        let (mut ponder, mut engine) = pondering(false);
+        ponder.makemove(&mut engine, "b1");
+        ponder.go(&mut engine, "go movetime 100\n");
+        assert_eq!(engine.0, ["moves a1", "moves b1", "go movetime 100"]);
+    }
+
+    #[test]
+    fn hit() {
+        let (mut ponder, mut engine) = pondering(true);
+        ponder.makemove(&mut engine, "b1");
+        ponder.go(&mut engine, "go movetime 100\n");
+        assert_eq!(
+            engine.0,
+            [
+                "moves a1",
+                "moves b1",
+                "go ponder movetime 100",
+                "ponderhit"
+            ]
+        );
+    }
+
+    #[test]
+    fn miss() {
+        let (mut ponder, mut engine) = pondering(true);
+        ponder.makemove(&mut engine, "c1");
+        ponder.go(&mut engine, "go movetime 100\n");
+        assert_eq!(
+            engine.0,
+            [
+                "moves a1",
+                "moves b1",
+                "go ponder movetime 100",
+                "stop",
+                "wait",
+                "position startpos",
+                "moves a1",
+                "moves c1",
+                "go movetime 100"
+            ]
+        );
+    }
+
+    #[test]
+    fn settle() {
+        // Stopped on the right guess, the engine already has the position
+        let (mut ponder, mut engine) = pondering(true);
+        ponder.makemove(&mut engine, "b1");
+        ponder.settle(&mut engine);
+        ponder.settle(&mut engine);
+        ponder.go(&mut engine, "go movetime 100\n");
+        assert_eq!(
+            engine.0,
+            [
+                "moves a1",
+                "moves b1",
+                "go ponder movetime 100",
+                "stop",
+                "wait",
+                "go movetime 100"
+            ]
+        );
+
+        // Stopped before the opponent has moved, the guess is taken back
+        let (mut ponder, mut engine) = pondering(true);
+        ponder.settle(&mut engine);
+        ponder.makemove(&mut engine, "b1");
+        assert_eq!(
+            engine.0,
+            [
+                "moves a1",
+                "moves b1",
+                "go ponder movetime 100",
+                "stop",
+                "wait",
+                "position startpos",
+                "moves a1",
+                "moves b1"
+            ]
+        );
+    }
+}
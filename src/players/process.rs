@@ -1,50 +1,118 @@
+use crate::players::commlog::{self, CommLog};
 use std::{
+    collections::VecDeque,
     io::{BufRead, BufReader, Write},
-    process::{Child, Command, Stdio},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
+// Engines that are currently running, so they can all be killed on abort
+static RUNNING: Mutex<Vec<u32>> = Mutex::new(vec![]);
+
+// How long an engine gets to exit by itself before it's killed
+const EXIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+// How many of the last stderr lines are kept for crash reports
+const STDERR_LINES: usize = 10;
+
+// Engines lead their own process group, so this takes whatever they (or their prefix) started with them
+pub fn kill(pid: u32) {
+    #[cfg(unix)]
+    unsafe {
+        libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+    }
+    #[cfg(not(unix))]
+    let _ = pid;
+}
+
+pub fn kill_all() {
+    let running = RUNNING.lock().expect("Lock fail thingy");
+    for pid in running.iter() {
+        kill(*pid);
+    }
+}
+
+// How an engine is started
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EngineCommand {
+    pub path: String,
+    // Another program the engine is run through, such as "nice -n 5"
+    pub prefix: String,
+    // The cores the engine is pinned to, any if empty
+    pub cpus: Vec<usize>,
+}
+
+// The affinity is set before the engine is executed so all of its threads inherit it
+#[cfg(target_os = "linux")]
+fn pin(command: &mut Command, cpus: &[usize]) {
+    use std::os::unix::process::CommandExt;
+
+    let mut set = unsafe { std::mem::zeroed::<libc::cpu_set_t>() };
+    for cpu in cpus {
+        unsafe { libc::CPU_SET(*cpu, &mut set) };
+    }
+
+    unsafe {
+        command.pre_exec(move || {
+            if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
 pub struct EngineProcess {
     child: Child,
-    callback: &'static dyn Fn(&str),
-    // stdin: ChildStdin,
-    // stdout: ChildStdout,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    // stdout has closed, the engine is gone or on its way out
+    is_eof: bool,
+    // Prefix for printing engine traffic to stdout
+    debug: Option<String>,
+    log: Option<Arc<Mutex<CommLog>>>,
+    stderr: Arc<Mutex<VecDeque<String>>>,
+    stderr_thread: Option<JoinHandle<()>>,
 }
 
 impl EngineProcess {
+    fn record(&mut self, direction: char, msg: &str) {
+        if let Some(prefix) = &self.debug {
+            println!(
+                "{:?}:{}{} {}",
+                thread::current().id(),
+                prefix,
+                direction,
+                msg.trim_end()
+            );
+        }
+        if let Some(log) = &self.log {
+            log.lock().expect("Lock fail thingy").write(direction, msg);
+        }
+    }
+
     pub fn send(&mut self, msg: &str) {
-        (self.callback)(msg);
-        let stdin = self.child.stdin.as_mut().unwrap();
-        let _ = stdin.write_all(msg.as_bytes());
+        self.record(commlog::SENT, msg);
+        let _ = self.stdin.write_all(msg.as_bytes());
     }
 
     pub fn wait(&mut self, msg: &str) {
-        let mut child_out = BufReader::new(self.child.stdout.as_mut().unwrap());
-
-        loop {
-            let mut line = String::new();
-            match child_out.read_line(&mut line) {
-                Ok(nbytes) => {
-                    (self.callback)(msg);
-                    if nbytes == 0 || line == msg {
-                        return;
-                    }
-                }
-                Err(err) => {
-                    println!("Error: {}", err);
-                    return;
-                }
-            }
-        }
+        self.wait_magic(&mut |line: &str| -> bool { line == msg });
     }
 
     pub fn wait_magic(&mut self, grr: &mut dyn FnMut(&str) -> bool) {
-        let mut child_out = BufReader::new(self.child.stdout.as_mut().unwrap());
-
         loop {
             let mut line = String::new();
-            match child_out.read_line(&mut line) {
+            match self.stdout.read_line(&mut line) {
+                // The engine has exited
+                Ok(0) => {
+                    self.is_eof = true;
+                    return;
+                }
                 Ok(_) => {
-                    (self.callback)(&line);
+                    self.record(commlog::RECEIVED, &line);
                     let ret = grr(&line);
                     if ret {
                         return;
@@ -60,29 +128,132 @@ impl EngineProcess {
 }
 
 impl EngineProcess {
-    pub fn new(path: &str, callback: &'static dyn Fn(&str)) -> Self {
-        let child = Command::new(path)
-            .args(["--game", "ataxx"])
+    #[must_use]
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    // Reaping the engine frees its pid for reuse, so it leaves RUNNING under the same lock kill_all takes
+    fn has_exited(&mut self) -> bool {
+        let mut running = RUNNING.lock().expect("Lock fail thingy");
+        let pid = self.child.id();
+        match self.child.try_wait() {
+            Ok(None) => false,
+            _ => {
+                running.retain(|running| *running != pid);
+                true
+            }
+        }
+    }
+
+    // The last lines the engine wrote to stderr
+    #[must_use]
+    pub fn stderr(&mut self) -> Vec<String> {
+        // Once the engine is gone, let the reader catch up with whatever it wrote last. It may not
+        // have been reaped yet, but the end of its stdout is as good a sign
+        if (self.is_eof || self.has_exited())
+            && let Some(handle) = self.stderr_thread.take()
+        {
+            let start = Instant::now();
+            while !handle.is_finished() && start.elapsed() < Duration::from_millis(100) {
+                thread::sleep(Duration::from_millis(5));
+            }
+        }
+
+        self.stderr
+            .lock()
+            .expect("Lock fail thingy")
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    pub fn new(
+        engine: &EngineCommand,
+        game: &str,
+        label: String,
+        debug: Option<String>,
+        log: Option<CommLog>,
+    ) -> Self {
+        let mut words = engine.prefix.split_whitespace();
+        let mut command = match words.next() {
+            Some(program) => {
+                let mut command = Command::new(program);
+                command.args(words).arg(&engine.path);
+                command
+            }
+            None => Command::new(&engine.path),
+        };
+        command
+            .args(["--game", game])
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .spawn()
-            .expect("Failed to launch engine process");
+            .stderr(Stdio::piped());
+
+        // Keep Ctrl-C in the terminal from reaching the engines, we decide when they stop
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+        #[cfg(target_os = "linux")]
+        if !engine.cpus.is_empty() {
+            pin(&mut command, &engine.cpus);
+        }
 
-        // let stdin = child.stdin.as_mut().unwrap();
+        let mut child = command.spawn().expect("Failed to launch engine process");
+        RUNNING.lock().expect("Lock fail thingy").push(child.id());
 
-        // let stdout = child.stdout.as_mut().unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let log = log.map(|log| Arc::new(Mutex::new(log)));
+
+        // stderr is read on its own thread so a chatty engine can't block on a full pipe
+        let stderr = Arc::new(Mutex::new(VecDeque::new()));
+        let stderr_thread = child.stderr.take().map(|child_err| {
+            let stderr = stderr.clone();
+            let log = log.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(child_err).lines().map_while(Result::ok) {
+                    eprintln!("<Stderr> {}: {}", label, line);
+                    if let Some(log) = &log {
+                        log.lock()
+                            .expect("Lock fail thingy")
+                            .write(commlog::STDERR, &line);
+                    }
+
+                    let mut stderr = stderr.lock().expect("Lock fail thingy");
+                    if stderr.len() >= STDERR_LINES {
+                        stderr.pop_front();
+                    }
+                    stderr.push_back(line);
+                }
+            })
+        });
 
         Self {
             child,
-            callback,
-            // stdin,
-            // stdout,
+            stdin,
+            stdout,
+            is_eof: false,
+            debug,
+            log,
+            stderr,
+            stderr_thread,
         }
     }
 }
 
 impl Drop for EngineProcess {
     fn drop(&mut self) {
-        let _ = self.child.wait();
+        // Give the engine a chance to quit, then make sure it does
+        let start = Instant::now();
+        let mut is_killed = false;
+        while !self.has_exited() {
+            if !is_killed && start.elapsed() >= EXIT_TIMEOUT {
+                kill(self.pid());
+                let _ = self.child.kill();
+                is_killed = true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
     }
 }
@@ -1,31 +1,47 @@
-use crate::players::{Player, clock::ClockType, process::EngineProcess};
+use crate::players::{
+    Player,
+    clock::{ClockType, Limits},
+    commlog::CommLog,
+    parse_bestmove, parse_info_time,
+    ponder::{Ponder, position_command},
+    process::{EngineCommand, EngineProcess},
+};
 use ataxx::{moves::Move, position::Position, result::GameResult, side::Side};
 
 pub struct UAIEngine {
     name: String,
+    game: String,
     process: EngineProcess,
     pos: Position,
-    clock: ClockType,
+    limits: Limits,
+    ponder: Ponder,
+    reported_time: Option<u128>,
 }
 
 impl UAIEngine {
-    pub fn new(name: &str, path: &str, clock: ClockType, debug: bool) -> Self {
-        if debug {
-            Self {
-                name: name.to_string(),
-                process: EngineProcess::new(path, &|line| {
-                    println!("{:?}:uai> {}", std::thread::current().id(), line);
-                }),
-                pos: Position::from_fen("startpos"),
-                clock,
-            }
-        } else {
-            Self {
-                name: name.to_string(),
-                process: EngineProcess::new(path, &|_line| {}),
-                pos: Position::from_fen("startpos"),
-                clock,
-            }
+    pub fn new(
+        name: &str,
+        command: &EngineCommand,
+        game: &str,
+        game_id: usize,
+        limits: Limits,
+        debug: bool,
+        log: Option<CommLog>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            game: game.to_string(),
+            process: EngineProcess::new(
+                command,
+                game,
+                format!("{} game {}", name, game_id),
+                debug.then(|| "uai".to_string()),
+                log,
+            ),
+            pos: Position::from_fen("startpos"),
+            ponder: Ponder::new(limits.ponder),
+            limits,
+            reported_time: None,
         }
     }
 }
@@ -38,9 +54,15 @@ impl Drop for UAIEngine {
 }
 
 impl Player for UAIEngine {
-    fn init(&mut self) {
+    fn init(&mut self) -> bool {
+        // UAI is only defined for Ataxx
+        if !self.game.eq_ignore_ascii_case("ataxx") {
+            return false;
+        }
+
         self.process.send("uai\n");
         self.process.wait("uaiok\n");
+        true
     }
 
     fn isready(&mut self) {
@@ -48,35 +70,52 @@ impl Player for UAIEngine {
         self.process.wait("readyok\n");
     }
 
+    fn set_option(&mut self, name: &str, value: &str) {
+        self.process
+            .send(&format!("setoption name {} value {}\n", name, value));
+    }
+
     fn set_position(&mut self, fen: &str) {
-        if fen == "startpos" {
-            self.pos = Position::from_fen("startpos");
-            self.process.send("position startpos\n");
-        } else {
-            self.pos = Position::from_fen(fen);
-            self.process.send(&format!("position fen {}\n", fen));
-        }
+        self.pos = Position::from_fen(fen);
+        self.ponder.set_position(fen);
+        self.process.send(&position_command(fen));
     }
 
-    fn get_move(&mut self) -> Option<String> {
-        self.process.send("go movetime 10\n");
-        let mut movestr = None;
+    fn get_move(&mut self, clocks: &[ClockType; 2]) -> Option<String> {
+        let go = self.limits.go_command(clocks, ["b", "w"]);
+        self.ponder.go(&mut self.process, &go);
+        let mut bestmove = None;
+        let mut reported_time = None;
         self.process.wait_magic(&mut |msg: &str| -> bool {
-            let found_bestmove = msg[0..8] == *"bestmove";
-            if found_bestmove {
-                movestr = Some(msg[9..msg.len() - 1].to_string());
-                true
-            } else {
-                false
+            if let Some(time) = parse_info_time(msg) {
+                reported_time = Some(time);
             }
+            bestmove = parse_bestmove(msg);
+            bestmove.is_some()
         });
-        movestr
+        self.reported_time = reported_time;
+        let (movestr, ponder) = bestmove?;
+        self.ponder.finish(ponder);
+        Some(movestr)
+    }
+
+    fn ponder(&mut self, clocks: &[ClockType; 2]) {
+        let go = self.limits.go_command(clocks, ["b", "w"]);
+        self.ponder.start(&mut self.process, &go);
+    }
+
+    fn get_reported_time(&mut self) -> Option<u128> {
+        self.reported_time
+    }
+
+    fn get_pid(&mut self) -> Option<u32> {
+        Some(self.process.pid())
     }
 
     fn makemove(&mut self, mvstr: &str) -> bool {
         let mv = Move::from_string(mvstr);
         self.pos.makemove(&mv);
-        self.process.send(&format!("moves {}\n", mvstr));
+        self.ponder.makemove(&mut self.process, mvstr);
         true
     }
 
@@ -105,7 +144,11 @@ impl Player for UAIEngine {
         }
     }
 
-    fn get_clock(&mut self) -> &mut super::clock::ClockType {
-        &mut self.clock
+    fn get_stderr(&mut self) -> Vec<String> {
+        self.process.stderr()
+    }
+
+    fn get_clock(&mut self) -> &mut ClockType {
+        &mut self.limits.clock
     }
 }
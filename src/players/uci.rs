@@ -1,4 +1,9 @@
-use crate::players::{Player, process::EngineProcess};
+use crate::players::{
+    Player,
+    clock::ClockType,
+    commlog::CommLog,
+    process::{EngineCommand, EngineProcess},
+};
 
 pub struct UCIEngine {
     name: String,
@@ -7,25 +12,29 @@ pub struct UCIEngine {
 }
 
 impl UCIEngine {
-    pub fn new(name: &str, path: &str, debug: bool) -> Self {
-        if debug {
-            Self {
-                name: name.to_string(),
-                process: EngineProcess::new(path, &|line| {
-                    println!("{}", line);
-                }),
-            }
-        } else {
-            Self {
-                name: name.to_string(),
-                process: EngineProcess::new(path, &|_line| {}),
-            }
+    pub fn new(
+        name: &str,
+        command: &EngineCommand,
+        game: &str,
+        game_id: usize,
+        debug: bool,
+        log: Option<CommLog>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            process: EngineProcess::new(
+                command,
+                game,
+                format!("{} game {}", name, game_id),
+                debug.then(|| "uci".to_string()),
+                log,
+            ),
         }
     }
 }
 
 impl Player for UCIEngine {
-    fn init(&mut self) {
+    fn init(&mut self) -> bool {
         todo!()
     }
 
@@ -33,14 +42,29 @@ impl Player for UCIEngine {
         todo!()
     }
 
+    fn set_option(&mut self, name: &str, value: &str) {
+        self.process
+            .send(&format!("setoption name {} value {}\n", name, value));
+    }
+
     fn set_position(&mut self, _fen: &str) {
         todo!()
     }
 
-    fn get_move(&mut self) -> Option<String> {
+    fn get_move(&mut self, _clocks: &[ClockType; 2]) -> Option<String> {
         todo!()
     }
 
+    fn ponder(&mut self, _clocks: &[ClockType; 2]) {}
+
+    fn get_reported_time(&mut self) -> Option<u128> {
+        None
+    }
+
+    fn get_pid(&mut self) -> Option<u32> {
+        Some(self.process.pid())
+    }
+
     fn makemove(&mut self, _mvstr: &str) -> bool {
         todo!()
     }
@@ -61,7 +85,11 @@ impl Player for UCIEngine {
         todo!()
     }
 
-    fn get_clock(&mut self) -> &mut super::clock::ClockType {
+    fn get_stderr(&mut self) -> Vec<String> {
+        self.process.stderr()
+    }
+
+    fn get_clock(&mut self) -> &mut ClockType {
         todo!()
     }
 }
@@ -1,32 +1,63 @@
-use crate::players::clock::ClockType;
-use crate::players::{Player, process::EngineProcess};
+use crate::players::clock::{ClockType, Limits};
+use crate::players::{
+    Player,
+    commlog::CommLog,
+    parse_bestmove, parse_info_time,
+    ponder::{Ponder, position_command},
+    process::{EngineCommand, EngineProcess},
+};
 
 pub struct UGIEngine {
     name: String,
+    game: String,
     process: EngineProcess,
-    clock: ClockType,
+    limits: Limits,
+    ponder: Ponder,
+    reported_time: Option<u128>,
 }
 
 impl UGIEngine {
-    pub fn new(name: &str, path: &str, clock: ClockType, debug: bool) -> Self {
-        if debug {
-            Self {
-                name: name.to_string(),
-                process: EngineProcess::new(path, &|line| {
-                    println!("{:?}:ugi> {}", std::thread::current().id(), line);
-                }),
-                clock,
-            }
-        } else {
-            Self {
-                name: name.to_string(),
-                process: EngineProcess::new(path, &|_line| {}),
-                clock,
-            }
+    pub fn new(
+        name: &str,
+        command: &EngineCommand,
+        game: &str,
+        game_id: usize,
+        limits: Limits,
+        debug: bool,
+        log: Option<CommLog>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            game: game.to_string(),
+            process: EngineProcess::new(
+                command,
+                game,
+                format!("{} game {}", name, game_id),
+                debug.then(|| "ugi".to_string()),
+                log,
+            ),
+            ponder: Ponder::new(limits.ponder),
+            limits,
+            reported_time: None,
         }
     }
 }
 
+// option name UCI_Variant type combo default ataxx var ataxx var chess
+#[must_use]
+fn parse_variants(line: &str) -> Vec<String> {
+    let mut variants = vec![];
+    let mut words = line.split_whitespace();
+    while let Some(word) = words.next() {
+        if word == "var"
+            && let Some(variant) = words.next()
+        {
+            variants.push(variant.to_lowercase());
+        }
+    }
+    variants
+}
+
 impl Drop for UGIEngine {
     fn drop(&mut self) {
         self.process.send("stop\n");
@@ -35,9 +66,30 @@ impl Drop for UGIEngine {
 }
 
 impl Player for UGIEngine {
-    fn init(&mut self) {
+    fn init(&mut self) -> bool {
         self.process.send("ugi\n");
-        self.process.wait("ugiok\n");
+
+        // Collect the games the engine advertises
+        let mut variants = vec![];
+        self.process.wait_magic(&mut |msg: &str| -> bool {
+            if msg.starts_with("option name UCI_Variant ") {
+                variants.extend(parse_variants(msg));
+            }
+            msg == "ugiok\n"
+        });
+
+        // Engines that don't advertise anything are trusted to play the game given by --game
+        if variants.is_empty() {
+            return true;
+        }
+
+        if !variants.contains(&self.game.to_lowercase()) {
+            return false;
+        }
+
+        self.process
+            .send(&format!("setoption name UCI_Variant value {}\n", self.game));
+        true
     }
 
     fn isready(&mut self) {
@@ -45,35 +97,54 @@ impl Player for UGIEngine {
         self.process.wait("readyok\n");
     }
 
+    fn set_option(&mut self, name: &str, value: &str) {
+        self.process
+            .send(&format!("setoption name {} value {}\n", name, value));
+    }
+
     fn set_position(&mut self, fen: &str) {
-        if fen == "startpos" {
-            self.process.send("position startpos\n");
-        } else {
-            self.process.send(&format!("position fen {}\n", fen));
-        }
+        self.ponder.set_position(fen);
+        self.process.send(&position_command(fen));
     }
 
-    fn get_move(&mut self) -> Option<String> {
-        self.process.send("go movetime 10\n");
-        let mut movestr = None;
+    fn get_move(&mut self, clocks: &[ClockType; 2]) -> Option<String> {
+        let go = self.limits.go_command(clocks, ["p1", "p2"]);
+        self.ponder.go(&mut self.process, &go);
+        let mut bestmove = None;
+        let mut reported_time = None;
         self.process.wait_magic(&mut |msg: &str| -> bool {
-            let found_bestmove = msg[0..8] == *"bestmove";
-            if found_bestmove {
-                movestr = Some(msg[9..msg.len() - 1].to_string());
-                true
-            } else {
-                false
+            if let Some(time) = parse_info_time(msg) {
+                reported_time = Some(time);
             }
+            bestmove = parse_bestmove(msg);
+            bestmove.is_some()
         });
-        movestr
+        self.reported_time = reported_time;
+        let (movestr, ponder) = bestmove?;
+        self.ponder.finish(ponder);
+        Some(movestr)
+    }
+
+    fn ponder(&mut self, clocks: &[ClockType; 2]) {
+        let go = self.limits.go_command(clocks, ["p1", "p2"]);
+        self.ponder.start(&mut self.process, &go);
+    }
+
+    fn get_reported_time(&mut self) -> Option<u128> {
+        self.reported_time
+    }
+
+    fn get_pid(&mut self) -> Option<u32> {
+        Some(self.process.pid())
     }
 
     fn makemove(&mut self, mvstr: &str) -> bool {
-        self.process.send(&format!("moves {}\n", mvstr));
+        self.ponder.makemove(&mut self.process, mvstr);
         true
     }
 
     fn is_gameover(&mut self) -> bool {
+        self.ponder.settle(&mut self.process);
         self.process.send("query gameover\n");
         let mut is_over = false;
         self.process.wait_magic(&mut |msg: &str| -> bool {
@@ -95,6 +166,7 @@ impl Player for UGIEngine {
     }
 
     fn get_turn(&mut self) -> Option<usize> {
+        self.ponder.settle(&mut self.process);
         self.process.send("query p1turn\n");
         let mut turn = None;
         self.process.wait_magic(&mut |msg: &str| -> bool {
@@ -112,6 +184,7 @@ impl Player for UGIEngine {
     }
 
     fn query_result(&mut self) -> Option<String> {
+        self.ponder.settle(&mut self.process);
         self.process.send("query result\n");
         let mut result = None;
         self.process.wait_magic(&mut |msg: &str| -> bool {
@@ -138,7 +211,35 @@ impl Player for UGIEngine {
         result
     }
 
+    fn get_stderr(&mut self) -> Vec<String> {
+        self.process.stderr()
+    }
+
     fn get_clock(&mut self) -> &mut ClockType {
-        &mut self.clock
+        &mut self.limits.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants() {
+        let tests = [
+            ("option name UCI_Variant type combo default ataxx\n", vec![]),
+            (
+                "option name UCI_Variant type combo default ataxx var ataxx\n",
+                vec!["ataxx"],
+            ),
+            (
+                "option name UCI_Variant type combo default ataxx var Ataxx var chess\n",
+                vec!["ataxx", "chess"],
+            ),
+        ];
+
+        for (line, expected) in tests {
+            assert_eq!(parse_variants(line), expected);
+        }
     }
 }
@@ -1,11 +1,13 @@
-#[derive(Default)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct WLD {
     pub w: usize,
     pub l: usize,
     pub d: usize,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct WLDPairs {
     pub ww: usize,
     pub wl: usize,
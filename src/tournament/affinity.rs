@@ -0,0 +1,146 @@
+use crate::tournament::Affinity;
+use std::ops::Range;
+
+// The most cores an affinity mask can hold
+const MAX_CORES: usize = 1024;
+
+// "auto", or a list of cores like "0,2,4-7"
+pub fn parse_affinity(text: &str) -> Result<Affinity, String> {
+    if text == "auto" {
+        return Ok(Affinity::Auto);
+    }
+
+    let mut cores = vec![];
+    for part in text.split(',').map(str::trim) {
+        let core = |value: &str| {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|core| *core < MAX_CORES)
+                .ok_or_else(|| format!("expected auto or a list of cores, got \"{}\"", text))
+        };
+
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (core(first)?, core(last)?);
+                if first > last {
+                    return Err(format!("core range {} is backwards", part));
+                }
+                cores.extend(first..=last);
+            }
+            None => cores.push(core(part)?),
+        }
+    }
+
+    let mut unique = cores.clone();
+    unique.sort();
+    unique.dedup();
+    if unique.len() != cores.len() {
+        return Err(format!("cores are listed more than once in \"{}\"", text));
+    }
+
+    Ok(Affinity::Cores(cores))
+}
+
+// The cores this process is allowed to run on
+#[must_use]
+fn available() -> Vec<usize> {
+    #[cfg(target_os = "linux")]
+    unsafe {
+        let mut set = std::mem::zeroed::<libc::cpu_set_t>();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) == 0 {
+            return (0..MAX_CORES)
+                .filter(|core| libc::CPU_ISSET(*core, &set))
+                .collect();
+        }
+    }
+
+    let count = std::thread::available_parallelism().map_or(1, |count| count.get());
+    (0..count).collect()
+}
+
+// Engines can't be pinned to cores that don't exist, or that this process can't use
+pub fn check_affinity(affinity: &Affinity) -> Result<(), String> {
+    let Affinity::Cores(cores) = affinity else {
+        return Ok(());
+    };
+
+    let available = available();
+    match cores.iter().find(|core| !available.contains(core)) {
+        Some(core) => Err(format!(
+            "core {} isn't available, expected some of {:?}",
+            core, available
+        )),
+        None => Ok(()),
+    }
+}
+
+// Each worker's engines get their own cores, workers share them if there aren't enough
+#[must_use]
+pub fn worker_cores(affinity: &Affinity, num_threads: usize, worker_id: usize) -> Vec<usize> {
+    let cores = match affinity {
+        Affinity::Auto => available(),
+        Affinity::Cores(cores) => cores.clone(),
+    };
+    if cores.is_empty() {
+        return vec![];
+    }
+
+    let per_worker = (cores.len() / num_threads.max(1)).max(1);
+    let group = worker_id % (cores.len() / per_worker);
+    cores[group * per_worker..(group + 1) * per_worker].to_vec()
+}
+
+// The cores of a run of workers, so tests sharing the threads don't share cores
+#[must_use]
+pub fn slot_cores(affinity: &Affinity, num_threads: usize, slots: Range<usize>) -> Vec<usize> {
+    let mut cores = slots
+        .flat_map(|worker_id| worker_cores(affinity, num_threads, worker_id))
+        .collect::<Vec<_>>();
+    cores.sort_unstable();
+    cores.dedup();
+    cores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_affinity("auto"), Ok(Affinity::Auto));
+        assert_eq!(
+            parse_affinity("0,2,4-7"),
+            Ok(Affinity::Cores(vec![0, 2, 4, 5, 6, 7]))
+        );
+        assert!(parse_affinity("").is_err());
+        assert!(parse_affinity("1,x").is_err());
+        assert!(parse_affinity("7-4").is_err());
+        assert!(parse_affinity("0-3,2").is_err());
+        assert!(parse_affinity("4096").is_err());
+    }
+
+    #[test]
+    fn cores() {
+        let affinity = Affinity::Cores(vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(worker_cores(&affinity, 4, 0), vec![0, 1]);
+        assert_eq!(worker_cores(&affinity, 4, 3), vec![6, 7]);
+        assert_eq!(worker_cores(&affinity, 3, 2), vec![4, 5]);
+
+        // More workers than cores
+        let affinity = Affinity::Cores(vec![2, 3]);
+        assert_eq!(worker_cores(&affinity, 4, 1), vec![3]);
+        assert_eq!(worker_cores(&affinity, 4, 2), vec![2]);
+
+        assert_eq!(slot_cores(&affinity, 4, 1..3), vec![2, 3]);
+        let affinity = Affinity::Cores(vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(slot_cores(&affinity, 4, 1..3), vec![2, 3, 4, 5]);
+
+        assert!(!worker_cores(&Affinity::Auto, 1, 0).is_empty());
+        assert_eq!(
+            check_affinity(&Affinity::Cores(vec![available()[0]])),
+            Ok(())
+        );
+        assert!(check_affinity(&Affinity::Cores(vec![MAX_CORES - 1])).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use crate::tournament::MAX_THREADS;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Command {
+    Status,
+    Pause,
+    Resume,
+    Stop,
+    Abort,
+    Threads(usize),
+    Help,
+}
+
+pub const COMMANDS: &str = "\
+Commands:
+  status      Print the current standings
+  pause       Stop starting new games
+  resume      Start new games again
+  stop        Finish the games being played then exit
+  abort       Abandon the games being played and exit
+  threads N   Play N games simultaneously
+  help        Print this message";
+
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+
+    match words.as_slice() {
+        ["status"] => Ok(Command::Status),
+        ["pause"] => Ok(Command::Pause),
+        ["resume"] => Ok(Command::Resume),
+        ["stop"] => Ok(Command::Stop),
+        ["abort"] => Ok(Command::Abort),
+        ["help"] => Ok(Command::Help),
+        ["threads", n] => match n.parse::<usize>() {
+            Ok(0) => Err("threads must be at least 1, use pause instead".to_string()),
+            Ok(n) if n >= MAX_THREADS => Err(format!("threads must be below {}", MAX_THREADS)),
+            Ok(n) => Ok(Command::Threads(n)),
+            Err(_) => Err(format!("threads expects a whole number, got \"{}\"", n)),
+        },
+        [] => Err("Empty command".to_string()),
+        _ => Err(format!("Unknown command \"{}\"", line.trim())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn success() {
+        assert_eq!(parse_command("status\n"), Ok(Command::Status));
+        assert_eq!(parse_command("pause\n"), Ok(Command::Pause));
+        assert_eq!(parse_command("resume\n"), Ok(Command::Resume));
+        assert_eq!(parse_command("stop\n"), Ok(Command::Stop));
+        assert_eq!(parse_command("abort\n"), Ok(Command::Abort));
+        assert_eq!(parse_command("help\n"), Ok(Command::Help));
+        assert_eq!(parse_command("  threads   4 \n"), Ok(Command::Threads(4)));
+    }
+
+    #[test]
+    fn failure() {
+        assert!(parse_command("\n").is_err());
+        assert!(parse_command("stat\n").is_err());
+        assert!(parse_command("threads\n").is_err());
+        assert!(parse_command("threads 0\n").is_err());
+        assert!(parse_command("threads 1000\n").is_err());
+        assert!(parse_command("threads four\n").is_err());
+        assert!(parse_command("stop now\n").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    stats::WLD,
+    tournament::{
+        AbortReason, Command, GameData, GameOutcome, TournamentSettings, observer::Observer,
+        results::Results,
+    },
+};
+
+// Tests in a queue share the terminal, so everything they print says which test it's from
+#[must_use]
+pub fn labelled(label: Option<&str>, msg: &str) -> String {
+    match label {
+        Some(label) => format!("[{}] {}", label, msg),
+        None => msg.to_string(),
+    }
+}
+
+pub fn print_results(results: &Results, settings: &TournamentSettings, label: Option<&str>) {
+    let [first, second, ..] = results.players.as_slice() else {
+        return;
+    };
+
+    let games = results.games_completed;
+    let print_short = games < 10;
+    let wld = WLD {
+        w: first.w,
+        l: first.l,
+        d: first.d,
+    };
+
+    if !print_short && games != 10 && !settings.verbose {
+        println!();
+    }
+
+    let score = format!(
+        "Score of {} vs {}: {} - {} - {} [{:.3}] {}",
+        first.name,
+        second.name,
+        wld.w,
+        wld.l,
+        wld.d,
+        wld.winrate(),
+        games
+    );
+    println!("{}", labelled(label, &score));
+
+    if print_short {
+        return;
+    }
+
+    let elo = format!("Elo difference:  {:.1} +/- {:.1}", first.elo, first.elo_err);
+    println!("{}", labelled(label, &elo));
+    for sprt in &results.sprt {
+        let line = format!(
+            "{}: llr {:.3} ({:.1}%), lbound {:.2}, ubound {:.2}",
+            if sprt.kind == "pentanomial" {
+                "nSPRT"
+            } else {
+                "SPRT"
+            },
+            sprt.llr,
+            0.0,
+            sprt.lbound,
+            sprt.ubound,
+        );
+        println!("{}", labelled(label, &line));
+    }
+}
+
+pub struct Console {
+    settings: TournamentSettings,
+    label: Option<String>,
+}
+
+impl Console {
+    pub fn new(settings: &TournamentSettings, label: Option<&str>) -> Self {
+        Self {
+            settings: settings.clone(),
+            label: label.map(String::from),
+        }
+    }
+
+    fn say(&self, msg: &str) {
+        println!("{}", labelled(self.label.as_deref(), msg));
+    }
+
+    #[must_use]
+    fn name(&self, id: usize) -> &str {
+        &self.settings.players[id].name
+    }
+}
+
+impl Observer for Console {
+    fn on_game_start(
+        &mut self,
+        id: usize,
+        fen_idx: usize,
+        _p1: usize,
+        _p2: usize,
+        _thread_id: usize,
+    ) {
+        if self.settings.verbose {
+            self.say(&format!(
+                "<Event::GameStart> Start game {} fen {}",
+                id, self.settings.fens[fen_idx]
+            ));
+        }
+    }
+
+    fn on_game_finish(&mut self, data: &GameData) {
+        if self.settings.verbose {
+            self.say(&format!(
+                "<Event::GameFinish> Finish game #{} -- {} vs {} -- result: {:?}",
+                data.id,
+                self.name(data.player1),
+                self.name(data.player2),
+                data.outcome
+            ));
+        }
+
+        if let Some(AbortReason::Crash(turn, stderr)) = &data.aborted {
+            let id = if *turn == 0 {
+                data.player1
+            } else {
+                data.player2
+            };
+            self.say(&format!(
+                "<Error> {} crashed in game {}",
+                self.name(id),
+                data.id
+            ));
+            for line in stderr {
+                self.say(&format!("  {}", line));
+            }
+        }
+    }
+
+    fn on_game_pair_result(
+        &mut self,
+        _p1: usize,
+        _p2: usize,
+        _r1: Option<GameOutcome>,
+        _r2: Option<GameOutcome>,
+    ) {
+        if self.settings.verbose {
+            self.say("<Event::GamePairResult> Pair complete");
+        }
+    }
+
+    fn on_player_create(&mut self, id: usize) {
+        if self.settings.verbose {
+            self.say(&format!("<Event::PlayerCreate> Create player: {}", id));
+        }
+    }
+
+    fn on_player_destroy(&mut self, id: usize) {
+        if self.settings.verbose {
+            self.say(&format!("<Event::PlayerDestroy> Destroy player: {}", id));
+        }
+    }
+
+    fn on_thread_start(&mut self, id: usize) {
+        if self.settings.verbose {
+            self.say(&format!("Start thread {}", id));
+        }
+    }
+
+    fn on_thread_finish(&mut self, id: usize) {
+        if self.settings.verbose {
+            self.say(&format!("Finish thread {}", id));
+        }
+    }
+
+    fn on_print_update(&mut self, results: &Results) {
+        print_results(results, &self.settings, self.label.as_deref());
+    }
+
+    fn on_command(&mut self, command: &Command) {
+        if self.settings.verbose {
+            self.say(&format!("<Event::Command> {:?}", command));
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use crate::tournament::{
+    Event, GameData, TournamentSettings,
+    observer::Observer,
+    play::{AbortReason, GameOutcome},
+};
+use serde_json::{Value, json};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+pub struct EventLog {
+    file: BufWriter<File>,
+    start: Instant,
+    settings: TournamentSettings,
+}
+
+#[must_use]
+fn outcome_json(outcome: Option<GameOutcome>) -> Value {
+    match outcome {
+        Some(GameOutcome::P1win) => json!("p1win"),
+        Some(GameOutcome::P2win) => json!("p2win"),
+        Some(GameOutcome::Draw) => json!("draw"),
+        None => Value::Null,
+    }
+}
+
+#[must_use]
+fn player_json(id: usize, settings: &TournamentSettings) -> Value {
+    json!({
+        "id": id,
+        "name": settings.players.get(id).map(|player| player.name.as_str()),
+    })
+}
+
+// Aborts record whose turn it was, reported as the player who had it
+#[must_use]
+fn termination_json(data: &GameData, settings: &TournamentSettings) -> Value {
+    let player = |turn: usize| {
+        let id = if turn == 0 {
+            data.player1
+        } else {
+            data.player2
+        };
+        player_json(id, settings)
+    };
+    match &data.aborted {
+        None => json!({"reason": "normal"}),
+        Some(AbortReason::Crash(turn, stderr)) => {
+            json!({"reason": "crash", "player": player(*turn), "stderr": stderr})
+        }
+        Some(AbortReason::Timeout(turn)) => json!({"reason": "timeout", "player": player(*turn)}),
+        Some(AbortReason::IllegalMove(turn, mv)) => {
+            json!({"reason": "illegal move", "player": player(*turn), "move": mv})
+        }
+        Some(AbortReason::MaxGameLength) => json!({"reason": "max game length"}),
+        Some(AbortReason::NoTurn) => json!({"reason": "no turn"}),
+        Some(AbortReason::PlayerStall) => json!({"reason": "player stall"}),
+    }
+}
+
+#[must_use]
+pub fn to_json(event: &Event, settings: &TournamentSettings) -> Value {
+    match event {
+        // Game
+        Event::GameStart(id, fen_idx, p1, p2, thread_id) => json!({
+            "event": "GameStart",
+            "game_id": id,
+            "thread_id": thread_id,
+            "opening": fen_idx,
+            "fen": settings.fens.get(*fen_idx),
+            "player1": player_json(*p1, settings),
+            "player2": player_json(*p2, settings),
+        }),
+        Event::GameFinish(data) => json!({
+            "event": "GameFinish",
+            "game_id": data.id,
+            "fen": data.fen,
+            "player1": player_json(data.player1, settings),
+            "player2": player_json(data.player2, settings),
+            "outcome": outcome_json(data.outcome),
+            "termination": termination_json(data, settings),
+            "ply": data.ply,
+            "moves": data.moves,
+            "timings": data.timings,
+        }),
+        Event::GamePairResult(p1, p2, r1, r2) => json!({
+            "event": "GamePairResult",
+            "player1": player_json(*p1, settings),
+            "player2": player_json(*p2, settings),
+            "outcome1": outcome_json(*r1),
+            "outcome2": outcome_json(*r2),
+        }),
+        // Tournament
+        Event::TournamentStart => json!({"event": "TournamentStart"}),
+        Event::TournamentFinish => json!({"event": "TournamentFinish"}),
+        // Players
+        Event::PlayerCreate(id) => json!({
+            "event": "PlayerCreate",
+            "player": player_json(*id, settings),
+        }),
+        Event::PlayerDestroy(id) => json!({
+            "event": "PlayerDestroy",
+            "player": player_json(*id, settings),
+        }),
+        // Threads
+        Event::ThreadStart(id) => json!({"event": "ThreadStart", "thread_id": id}),
+        Event::ThreadFinish(id) => json!({"event": "ThreadFinish", "thread_id": id}),
+        // Results
+        Event::PrintUpdate => json!({"event": "PrintUpdate"}),
+        // Other
+        Event::Command(command) => json!({
+            "event": "Command",
+            "command": format!("{:?}", command),
+        }),
+    }
+}
+
+impl EventLog {
+    pub fn new(path: &str, settings: &TournamentSettings) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open event log {}: {}", path, e))?;
+
+        Ok(Self {
+            file: BufWriter::new(file),
+            start: Instant::now(),
+            settings: settings.clone(),
+        })
+    }
+}
+
+impl Observer for EventLog {
+    fn on_event(&mut self, event: &Event) {
+        let mut record = to_json(event, &self.settings);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis())
+            .unwrap_or_default();
+        record["timestamp_ms"] = json!(timestamp);
+        record["elapsed_ms"] = json!(self.start.elapsed().as_millis());
+
+        // Flushed every line so the log is usable even if we're killed
+        let result = writeln!(self.file, "{}", record).and_then(|_| self.file.flush());
+        if let Err(err) = result {
+            println!("<Error> Failed to write event log: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tournament::{MoveTiming, PlayerSettings};
+
+    #[test]
+    fn success() {
+        let settings = TournamentSettings {
+            players: vec![
+                PlayerSettings {
+                    name: "Player 1".to_string(),
+                    ..Default::default()
+                },
+                PlayerSettings {
+                    name: "Player 2".to_string(),
+                    ..Default::default()
+                },
+            ],
+            fens: vec!["startpos".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            to_json(&Event::GameStart(4, 0, 1, 0, 2), &settings),
+            json!({
+                "event": "GameStart",
+                "game_id": 4,
+                "thread_id": 2,
+                "opening": 0,
+                "fen": "startpos",
+                "player1": {"id": 1, "name": "Player 2"},
+                "player2": {"id": 0, "name": "Player 1"},
+            })
+        );
+
+        let data = GameData {
+            id: 4,
+            outcome: Some(GameOutcome::P2win),
+            player1: 1,
+            player2: 0,
+            ply: 1,
+            fen: "startpos".to_string(),
+            moves: vec!["z9".to_string()],
+            timings: vec![MoveTiming {
+                thinking: 98,
+                latency: 2,
+                reported: Some(97),
+            }],
+            aborted: Some(AbortReason::IllegalMove(0, "z9".to_string())),
+        };
+        assert_eq!(
+            to_json(&Event::GameFinish(data), &settings),
+            json!({
+                "event": "GameFinish",
+                "game_id": 4,
+                "fen": "startpos",
+                "player1": {"id": 1, "name": "Player 2"},
+                "player2": {"id": 0, "name": "Player 1"},
+                "outcome": "p2win",
+                "termination": {
+                    "reason": "illegal move",
+                    "player": {"id": 1, "name": "Player 2"},
+                    "move": "z9",
+                },
+                "ply": 1,
+                "moves": ["z9"],
+                "timings": [{"thinking": 98, "latency": 2, "reported": 97}],
+            })
+        );
+
+        assert_eq!(
+            to_json(
+                &Event::GamePairResult(0, 1, Some(GameOutcome::Draw), None),
+                &settings
+            ),
+            json!({
+                "event": "GamePairResult",
+                "player1": {"id": 0, "name": "Player 1"},
+                "player2": {"id": 1, "name": "Player 2"},
+                "outcome1": "draw",
+                "outcome2": null,
+            })
+        );
+    }
+}
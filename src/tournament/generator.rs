@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
 enum Pairing {
     RoundRobin,
 }
@@ -8,8 +11,11 @@ pub struct Generator {
     pub num_players: usize,
     pub num_openings: usize,
     pub num_games: Option<usize>,
+    pub requeue: Vec<usize>,
+    pub in_progress: BTreeSet<usize>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Work {
     pub game_id: usize,
     pub player1: usize,
@@ -26,11 +32,31 @@ impl Generator {
             num_players,
             num_openings,
             num_games,
+            requeue: vec![],
+            in_progress: BTreeSet::new(),
+        }
+    }
+
+    #[must_use]
+    fn create(&self, game_id: usize) -> Work {
+        match self.asd {
+            Pairing::RoundRobin => Work {
+                game_id,
+                player1: game_id % 2,
+                player2: 1 - (game_id % 2),
+                fen_idx: (game_id / 2) % self.num_openings,
+            },
         }
     }
 
     #[must_use]
     pub fn next(&mut self) -> Option<Work> {
+        // Games that were handed out but never finished come first
+        if let Some(game_id) = self.requeue.pop() {
+            self.in_progress.insert(game_id);
+            return Some(self.create(game_id));
+        }
+
         // Number of games limit reached
         if let Some(max) = self.num_games
             && self.id >= max
@@ -39,18 +65,64 @@ impl Generator {
         }
 
         // Create work
-        let work = match self.asd {
-            Pairing::RoundRobin => Some(Work {
-                game_id: self.id,
-                player1: self.id % 2,
-                player2: 1 - (self.id % 2),
-                fen_idx: (self.id / 2) % self.num_openings,
-            }),
-        };
+        let work = self.create(self.id);
 
         // Update state
+        self.in_progress.insert(self.id);
         self.id += 1;
 
-        work
+        Some(work)
+    }
+
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.requeue.is_empty() && self.num_games.is_some_and(|max| self.id >= max)
+    }
+
+    pub fn finish(&mut self, game_id: usize) {
+        self.in_progress.remove(&game_id);
+    }
+
+    // Hand a game back to be played again, for when a remote worker is lost
+    pub fn reissue(&mut self, game_id: usize) {
+        if self.in_progress.remove(&game_id) {
+            self.requeue.push(game_id);
+        }
+    }
+
+    #[must_use]
+    pub fn pending(&self) -> Vec<usize> {
+        self.requeue
+            .iter()
+            .chain(self.in_progress.iter())
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requeue() {
+        let mut generator = Generator::new(2, 4, Some(4));
+        generator.id = 3;
+        generator.requeue = vec![1];
+
+        let work = generator.next().unwrap();
+        assert_eq!((work.game_id, work.player1, work.player2), (1, 1, 0));
+        let work = generator.next().unwrap();
+        assert_eq!((work.game_id, work.player1, work.player2), (3, 1, 0));
+        assert!(generator.next().is_none());
+
+        assert_eq!(generator.pending(), vec![1, 3]);
+        generator.finish(1);
+        assert_eq!(generator.pending(), vec![3]);
+
+        generator.reissue(3);
+        generator.reissue(3);
+        assert_eq!(generator.requeue, vec![3]);
+        assert_eq!(generator.next().unwrap().game_id, 3);
     }
 }
@@ -0,0 +1,341 @@
+use crate::tournament::{
+    Command, GameData, TournamentSettings, observer::Observer, results::Results,
+};
+use serde_json::{Value, json};
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+// How long a client gets to send its request and take the response, it's served on the only thread
+const STREAM_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct ActiveGame {
+    fen_idx: usize,
+    player1: usize,
+    player2: usize,
+    thread_id: usize,
+    start: Instant,
+}
+
+#[derive(Default)]
+struct Status {
+    results: Option<Results>,
+    games: BTreeMap<usize, ActiveGame>,
+    // The game each thread is playing, if any
+    threads: BTreeMap<usize, Option<usize>>,
+    finished_threads: Vec<usize>,
+    is_paused: bool,
+}
+
+pub struct StatusPage {
+    status: Arc<Mutex<Status>>,
+}
+
+#[must_use]
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[must_use]
+fn to_json(status: &Status, settings: &TournamentSettings) -> Value {
+    let name = |id: usize| settings.players.get(id).map(|player| player.name.as_str());
+
+    let games = status
+        .games
+        .iter()
+        .map(|(id, game)| {
+            json!({
+                "game_id": id,
+                "opening": game.fen_idx,
+                "player1": name(game.player1),
+                "player2": name(game.player2),
+                "thread_id": game.thread_id,
+                "elapsed_seconds": game.start.elapsed().as_secs_f32(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut threads = status
+        .threads
+        .iter()
+        .map(|(id, game)| match game {
+            Some(game) => json!({"thread_id": id, "state": "playing", "game_id": game}),
+            None if status.is_paused => json!({"thread_id": id, "state": "paused"}),
+            None => json!({"thread_id": id, "state": "idle"}),
+        })
+        .collect::<Vec<_>>();
+    threads.extend(
+        status
+            .finished_threads
+            .iter()
+            .map(|id| json!({"thread_id": id, "state": "finished"})),
+    );
+
+    json!({
+        "paused": status.is_paused,
+        "results": status.results,
+        "active_games": games,
+        "threads": threads,
+    })
+}
+
+#[must_use]
+fn to_html(status: &Status, settings: &TournamentSettings) -> String {
+    let json = to_json(status, settings);
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta http-equiv=\"refresh\" content=\"5\">\n<title>CuterGames</title>\n</head>\n<body>\n",
+    );
+
+    html += &format!("<h1>CuterGames - {}</h1>\n", escape(&settings.game));
+
+    match &status.results {
+        Some(results) => {
+            html += &format!(
+                "<p>{} games completed in {:.0}s ({:.2} games/s){}</p>\n",
+                results.games_completed,
+                results.elapsed_seconds,
+                results.games_per_second,
+                if status.is_paused { ", paused" } else { "" }
+            );
+
+            html += "<h2>Standings</h2>\n<table>\n\
+                     <tr><th>Name</th><th>Games</th><th>W</th><th>L</th><th>D</th><th>Elo</th></tr>\n";
+            for player in &results.players {
+                html += &format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.1} +/- {:.1}</td></tr>\n",
+                    escape(&player.name),
+                    player.played,
+                    player.w,
+                    player.l,
+                    player.d,
+                    player.elo,
+                    player.elo_err
+                );
+            }
+            html += "</table>\n";
+
+            for sprt in &results.sprt {
+                html += &format!(
+                    "<p>SPRT ({}): LLR {:.2} ({:.2}, {:.2}) {:?}</p>\n",
+                    sprt.kind, sprt.llr, sprt.lbound, sprt.ubound, sprt.status
+                );
+            }
+        }
+        None => html += "<p>No games completed yet</p>\n",
+    }
+
+    html += "<h2>Games</h2>\n<table>\n\
+             <tr><th>Game</th><th>Opening</th><th>Player 1</th><th>Player 2</th><th>Time</th></tr>\n";
+    for game in json["active_games"].as_array().into_iter().flatten() {
+        html += &format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.1}s</td></tr>\n",
+            game["game_id"],
+            game["opening"],
+            escape(game["player1"].as_str().unwrap_or_default()),
+            escape(game["player2"].as_str().unwrap_or_default()),
+            game["elapsed_seconds"].as_f64().unwrap_or_default()
+        );
+    }
+    html += "</table>\n";
+
+    html += "<h2>Threads</h2>\n<table>\n<tr><th>Thread</th><th>State</th></tr>\n";
+    for thread in json["threads"].as_array().into_iter().flatten() {
+        let state = match thread["game_id"].as_u64() {
+            Some(game) => format!("playing game {}", game),
+            None => thread["state"].as_str().unwrap_or_default().to_string(),
+        };
+        html += &format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            thread["thread_id"], state
+        );
+    }
+    html += "</table>\n</body>\n</html>\n";
+
+    html
+}
+
+fn respond(mut stream: TcpStream, status: &Mutex<Status>, settings: &TournamentSettings) {
+    if stream.set_read_timeout(Some(STREAM_TIMEOUT)).is_err()
+        || stream.set_write_timeout(Some(STREAM_TIMEOUT)).is_err()
+    {
+        return;
+    }
+
+    let mut request = String::new();
+    if BufReader::new(&stream).read_line(&mut request).is_err() {
+        return;
+    }
+
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let (code, content_type, body) = {
+        let status = status.lock().expect("Lock fail thingy");
+        match path {
+            "/" | "/index.html" => ("200 OK", "text/html", to_html(&status, settings)),
+            "/status.json" | "/json" => (
+                "200 OK",
+                "application/json",
+                to_json(&status, settings).to_string(),
+            ),
+            _ => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+        }
+    };
+
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        content_type,
+        body.len(),
+        body
+    );
+}
+
+impl StatusPage {
+    pub fn new(address: &str, settings: &TournamentSettings) -> Result<Self, String> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+        let status = Arc::new(Mutex::new(Status::default()));
+
+        let nstatus = status.clone();
+        let nsettings = settings.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                respond(stream, &nstatus, &nsettings);
+            }
+        });
+
+        Ok(Self { status })
+    }
+}
+
+impl Observer for StatusPage {
+    fn on_game_start(&mut self, id: usize, fen_idx: usize, p1: usize, p2: usize, thread_id: usize) {
+        let mut status = self.status.lock().expect("Lock fail thingy");
+        status.games.insert(
+            id,
+            ActiveGame {
+                fen_idx,
+                player1: p1,
+                player2: p2,
+                thread_id,
+                start: Instant::now(),
+            },
+        );
+        status.threads.insert(thread_id, Some(id));
+    }
+
+    fn on_game_finish(&mut self, data: &GameData) {
+        let mut status = self.status.lock().expect("Lock fail thingy");
+        if let Some(game) = status.games.remove(&data.id) {
+            status.threads.insert(game.thread_id, None);
+        }
+    }
+
+    fn on_thread_start(&mut self, id: usize) {
+        let mut status = self.status.lock().expect("Lock fail thingy");
+        status.threads.insert(id, None);
+        status.finished_threads.retain(|thread| *thread != id);
+    }
+
+    fn on_thread_finish(&mut self, id: usize) {
+        let mut status = self.status.lock().expect("Lock fail thingy");
+        // Aborted games never finish
+        if let Some(Some(game)) = status.threads.remove(&id) {
+            status.games.remove(&game);
+        }
+        status.finished_threads.push(id);
+    }
+
+    fn on_print_update(&mut self, results: &Results) {
+        self.status.lock().expect("Lock fail thingy").results = Some(results.clone());
+    }
+
+    fn on_command(&mut self, command: &Command) {
+        let mut status = self.status.lock().expect("Lock fail thingy");
+        match command {
+            Command::Pause => status.is_paused = true,
+            Command::Resume => status.is_paused = false,
+            _ => {}
+        }
+    }
+
+    fn on_end(&mut self, results: &Results) {
+        self.status.lock().expect("Lock fail thingy").results = Some(results.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tournament::PlayerSettings;
+    use std::io::Read;
+
+    fn get(address: &str, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn success() {
+        let settings = TournamentSettings {
+            players: vec![
+                PlayerSettings {
+                    name: "<Player 1>".to_string(),
+                    ..Default::default()
+                },
+                PlayerSettings {
+                    name: "Player 2".to_string(),
+                    ..Default::default()
+                },
+            ],
+            fens: vec!["startpos".to_string()],
+            ..Default::default()
+        };
+
+        let mut page = StatusPage::new("127.0.0.1:0", &settings).unwrap();
+        page.on_thread_start(0);
+        page.on_thread_start(1);
+        page.on_game_start(7, 0, 0, 1, 1);
+
+        let status = page.status.lock().unwrap();
+        let json = to_json(&status, &settings);
+        assert_eq!(json["active_games"][0]["game_id"], 7);
+        assert_eq!(json["active_games"][0]["player1"], "<Player 1>");
+        assert_eq!(json["threads"][0]["state"], "idle");
+        assert_eq!(json["threads"][1]["state"], "playing");
+        assert!(to_html(&status, &settings).contains("&lt;Player 1&gt;"));
+    }
+
+    #[test]
+    fn serve() {
+        // Find a free port to listen on
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let _page = StatusPage::new(&address, &TournamentSettings::default()).unwrap();
+
+        let response = get(&address, "/status.json");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\"active_games\":[]"));
+
+        let response = get(&address, "/");
+        assert!(response.contains("text/html"));
+        assert!(response.contains("No games completed yet"));
+
+        assert!(get(&address, "/nope").starts_with("HTTP/1.1 404"));
+    }
+}
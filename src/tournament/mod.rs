@@ -1,31 +1,90 @@
-use crate::{
-    stats::{WLD, WLDPairs},
-    tournament::play::GameOutcome,
+use crate::players::{
+    Protocol,
+    clock::{ClockType, Limits},
+    get_protocol,
 };
+use crate::stats::{WLD, WLDPairs};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 
+pub(crate) use affinity::{check_affinity, parse_affinity};
+pub use command::Command;
+pub use observer::Observer;
+pub use play::{AbortReason, GameOutcome, MoveTiming};
+pub use queue::run_queue;
+pub use remote::run_worker;
+pub(crate) use spsa::parse_spec;
+
+mod affinity;
+mod command;
+mod console;
+mod eventlog;
 mod generator;
+mod http;
+mod observer;
+mod on_command;
 mod on_game_finish;
 mod on_game_pair_result;
-mod on_game_start;
 mod on_player_create;
 mod on_player_destroy;
-mod on_print_results;
-mod on_thread_finish;
-mod on_thread_start;
+mod pgn;
 mod play;
+mod queue;
+mod remote;
+pub mod results;
 pub mod run;
+mod spsa;
+mod state;
 mod worker;
 
-#[derive(Default, Debug, PartialEq, Clone)]
+// Local threads are numbered below this and remote workers from it up
+pub const MAX_THREADS: usize = 1000;
+
+#[derive(Default, Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct PlayerSettings {
     pub name: String,
     pub path: String,
     pub proto: String,
     pub parameters: String,
+    pub prefix: String,
+    pub tc: Option<ClockType>,
+    pub movetime: Option<usize>,
+    pub depth: Option<usize>,
+    pub nodes: Option<usize>,
+    pub ponder: bool,
     pub debug: bool,
 }
 
-#[derive(Debug, PartialEq, Clone, Default)]
+impl PlayerSettings {
+    // Players run as a program of their own
+    #[must_use]
+    pub fn is_engine(&self) -> bool {
+        !matches!(
+            get_protocol(&self.proto),
+            Some(Protocol::Human | Protocol::Builtin)
+        )
+    }
+
+    #[must_use]
+    pub fn is_human(&self) -> bool {
+        get_protocol(&self.proto) == Some(Protocol::Human)
+    }
+
+    #[must_use]
+    pub fn limits(&self) -> Limits {
+        // Humans and the builtin players, which search to a depth, play without a clock unless given one
+        let tc = self.tc.clone().or_else(|| {
+            (!self.is_engine() && self.movetime.is_none()).then_some(ClockType::Infinite)
+        });
+        Limits {
+            ponder: self.ponder,
+            ..Limits::new(tc, self.movetime, self.depth, self.nodes)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SPRTSettings {
     pub alpha: f32,
     pub beta: f32,
@@ -34,19 +93,115 @@ pub struct SPRTSettings {
     pub autostop: bool,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct TuneParameter {
+    pub name: String,
+    pub start: f32,
+    pub min: f32,
+    pub max: f32,
+    pub c_end: f32,
+    pub r_end: f32,
+    pub is_integer: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TuneSettings {
+    pub params: Vec<TuneParameter>,
+    pub output: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Affinity {
+    // Every core this process can use
+    Auto,
+    Cores(Vec<usize>),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Schedule {
+    // Tests run in order and share the threads evenly
+    RoundRobin,
+    // Tests run highest priority first and share the threads in proportion to their priority
+    Priority,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct QueueTest {
+    pub name: String,
+    pub priority: usize,
+    pub settings: TournamentSettings,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct QueueSettings {
+    pub tests: Vec<QueueTest>,
+    pub num_threads: usize,
+    pub active: usize,
+    pub schedule: Schedule,
+    // Take commands from the terminal and stop on Ctrl-C, left to the caller when used as a library
+    pub interactive: bool,
+}
+
+impl Default for QueueSettings {
+    fn default() -> Self {
+        Self {
+            tests: vec![],
+            num_threads: 1,
+            active: 1,
+            schedule: Schedule::RoundRobin,
+            interactive: false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct TimingSettings {
+    pub margin: u128,
+    pub trust_engine: bool,
+    // How long a player without a time limit, searching to a depth or node count, can think before it's stalled
+    pub stall_time: u128,
+}
+
+impl Default for TimingSettings {
+    fn default() -> Self {
+        Self {
+            margin: 10,
+            trust_engine: false,
+            stall_time: 60000,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct TournamentSettings {
     pub players: Vec<PlayerSettings>,
+    pub game: String,
     pub fens: Vec<String>,
     pub num_threads: usize,
     pub num_games: Option<usize>,
+    pub timing: TimingSettings,
     pub update_frequency: usize,
     pub sprt_trinomial: Option<SPRTSettings>,
     pub sprt_pentanomial: Option<SPRTSettings>,
+    pub tune: Option<TuneSettings>,
+    pub pgn: Option<String>,
+    pub results_json: Option<String>,
+    pub results_csv: Option<String>,
+    pub eventlog: Option<String>,
+    pub log: Option<String>,
+    pub http: Option<String>,
+    pub listen: Option<String>,
+    pub worker: Option<String>,
+    pub affinity: Option<Affinity>,
+    pub queue: Option<String>,
+    pub state: Option<String>,
+    pub resume: bool,
     pub verbose: bool,
+    // Take commands from the terminal, stop on Ctrl-C and print progress, left to the caller when used as a library
+    pub interactive: bool,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 struct PlayerStatistics {
     name: String,
     played: usize,
@@ -57,24 +212,29 @@ struct PlayerStatistics {
     wld_pairs: WLDPairs,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 struct TournamentStatistics {
     games_completed: usize,
     players_created: usize,
     players_destroyed: usize,
 }
 
-struct GameData {
-    id: usize,
-    outcome: Option<GameOutcome>,
-    player1: usize,
-    player2: usize,
-    ply: usize,
+#[derive(Serialize, Deserialize)]
+pub struct GameData {
+    pub id: usize,
+    pub outcome: Option<GameOutcome>,
+    pub player1: usize,
+    pub player2: usize,
+    pub ply: usize,
+    pub fen: String,
+    pub moves: Vec<String>,
+    pub timings: Vec<MoveTiming>,
+    pub aborted: Option<AbortReason>,
 }
 
-enum Event {
+pub enum Event {
     // Game
-    GameStart(usize, usize),
+    GameStart(usize, usize, usize, usize, usize),
     GameFinish(GameData),
     GamePairResult(usize, usize, Option<GameOutcome>, Option<GameOutcome>),
     // Tournament
@@ -82,27 +242,51 @@ enum Event {
     TournamentFinish,
     // Players
     PlayerCreate(usize),
-    PlayerDestroy,
+    PlayerDestroy(usize),
     // Threads
     ThreadStart(usize),
     ThreadFinish(usize),
     // Results
     PrintUpdate,
     // Other
-    KeyPress,
+    Command(Command),
+}
+
+#[derive(Default)]
+struct Controls {
+    should_stop: AtomicBool,
+    should_abort: AtomicBool,
+    is_paused: AtomicBool,
+    num_threads: AtomicUsize,
 }
 
 impl Default for TournamentSettings {
     fn default() -> Self {
         Self {
             players: vec![],
+            game: "ataxx".to_string(),
             fens: vec![],
             num_threads: 1,
             num_games: None,
+            timing: TimingSettings::default(),
             update_frequency: 10,
             sprt_trinomial: None,
             sprt_pentanomial: None,
+            tune: None,
+            pgn: None,
+            results_json: None,
+            results_csv: None,
+            eventlog: None,
+            log: None,
+            http: None,
+            listen: None,
+            worker: None,
+            affinity: None,
+            queue: None,
+            state: None,
+            resume: false,
             verbose: false,
+            interactive: false,
         }
     }
 }
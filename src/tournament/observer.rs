@@ -0,0 +1,139 @@
+use crate::tournament::{Command, Event, GameData, GameOutcome, results::Results};
+
+pub trait Observer {
+    // Every event as it's received, before the hook for it is called
+    fn on_event(&mut self, _event: &Event) {}
+
+    // Game
+    fn on_game_start(
+        &mut self,
+        _id: usize,
+        _fen_idx: usize,
+        _p1: usize,
+        _p2: usize,
+        _thread_id: usize,
+    ) {
+    }
+
+    fn on_game_finish(&mut self, _data: &GameData) {}
+
+    fn on_game_pair_result(
+        &mut self,
+        _p1: usize,
+        _p2: usize,
+        _r1: Option<GameOutcome>,
+        _r2: Option<GameOutcome>,
+    ) {
+    }
+
+    // Tournament
+    fn on_tournament_start(&mut self) {}
+
+    fn on_tournament_finish(&mut self) {}
+
+    // Players
+    fn on_player_create(&mut self, _id: usize) {}
+
+    fn on_player_destroy(&mut self, _id: usize) {}
+
+    // Threads
+    fn on_thread_start(&mut self, _id: usize) {}
+
+    fn on_thread_finish(&mut self, _id: usize) {}
+
+    // Results
+    fn on_print_update(&mut self, _results: &Results) {}
+
+    // Other
+    fn on_command(&mut self, _command: &Command) {}
+
+    // The tournament is over, called once with the final results
+    fn on_end(&mut self, _results: &Results) {}
+}
+
+// Results are only collected when an update is printed
+pub(super) fn notify(observer: &mut dyn Observer, event: &Event, results: Option<&Results>) {
+    observer.on_event(event);
+
+    match event {
+        // Game
+        Event::GameStart(id, fen_idx, p1, p2, thread_id) => {
+            observer.on_game_start(*id, *fen_idx, *p1, *p2, *thread_id)
+        }
+        Event::GameFinish(data) => observer.on_game_finish(data),
+        Event::GamePairResult(p1, p2, r1, r2) => observer.on_game_pair_result(*p1, *p2, *r1, *r2),
+        // Tournament
+        Event::TournamentStart => observer.on_tournament_start(),
+        Event::TournamentFinish => observer.on_tournament_finish(),
+        // Players
+        Event::PlayerCreate(id) => observer.on_player_create(*id),
+        Event::PlayerDestroy(id) => observer.on_player_destroy(*id),
+        // Threads
+        Event::ThreadStart(id) => observer.on_thread_start(*id),
+        Event::ThreadFinish(id) => observer.on_thread_finish(*id),
+        // Results
+        Event::PrintUpdate => {
+            if let Some(results) = results {
+                observer.on_print_update(results);
+            }
+        }
+        // Other
+        Event::Command(command) => observer.on_command(command),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Counter {
+        events: usize,
+        games: usize,
+        threads: usize,
+        updates: usize,
+    }
+
+    impl Observer for Counter {
+        fn on_event(&mut self, _event: &Event) {
+            self.events += 1;
+        }
+
+        fn on_game_start(
+            &mut self,
+            _id: usize,
+            _fen_idx: usize,
+            _p1: usize,
+            _p2: usize,
+            _thread_id: usize,
+        ) {
+            self.games += 1;
+        }
+
+        fn on_thread_start(&mut self, _id: usize) {
+            self.threads += 1;
+        }
+
+        fn on_print_update(&mut self, _results: &Results) {
+            self.updates += 1;
+        }
+    }
+
+    #[test]
+    fn success() {
+        let mut counter = Counter::default();
+
+        notify(&mut counter, &Event::TournamentStart, None);
+        notify(&mut counter, &Event::ThreadStart(0), None);
+        notify(&mut counter, &Event::GameStart(0, 0, 0, 1, 0), None);
+        notify(&mut counter, &Event::GameStart(1, 0, 1, 0, 0), None);
+        notify(&mut counter, &Event::PlayerCreate(0), None);
+        // No results, no update
+        notify(&mut counter, &Event::PrintUpdate, None);
+
+        assert_eq!(counter.events, 6);
+        assert_eq!(counter.games, 2);
+        assert_eq!(counter.threads, 1);
+        assert_eq!(counter.updates, 0);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{
+    players,
+    tournament::{
+        Controls, TournamentSettings,
+        command::{COMMANDS, Command},
+        console::{labelled, print_results},
+        results::Results,
+    },
+};
+use std::sync::atomic::Ordering;
+
+pub fn on_command(
+    command: &Command,
+    controls: &Controls,
+    results: &Results,
+    settings: &TournamentSettings,
+    label: Option<&str>,
+) {
+    let say = |msg: &str| println!("{}", labelled(label, msg));
+    match command {
+        Command::Status => {
+            if results.games_completed == 0 {
+                say("No games completed yet");
+            } else {
+                print_results(results, settings, label);
+            }
+        }
+        Command::Pause => {
+            controls.is_paused.store(true, Ordering::Relaxed);
+            say("Paused, games being played will still finish");
+        }
+        Command::Resume => {
+            controls.is_paused.store(false, Ordering::Relaxed);
+            say("Resumed");
+        }
+        Command::Stop => {
+            controls.should_stop.store(true, Ordering::Relaxed);
+            say("Stopping once the games being played finish");
+        }
+        Command::Abort => {
+            controls.should_abort.store(true, Ordering::Relaxed);
+            controls.should_stop.store(true, Ordering::Relaxed);
+            players::kill_all();
+            say("Aborting the games being played");
+        }
+        Command::Threads(n) => {
+            controls.num_threads.store(*n, Ordering::Relaxed);
+            say(&format!("Playing {} games simultaneously", n));
+        }
+        Command::Help => println!("{}", COMMANDS),
+    }
+}
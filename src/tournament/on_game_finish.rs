@@ -2,7 +2,7 @@ use crate::{
     sprt,
     tournament::{
         Event, GameData, PlayerStatistics, TournamentSettings, TournamentStatistics,
-        play::GameOutcome,
+        play::{AbortReason, GameOutcome},
     },
 };
 use std::{collections::HashMap, sync::mpsc::Sender};
@@ -21,19 +21,33 @@ pub fn on_game_finish(
     // Current game ID can't already exist
     debug_assert!(!pair_store.contains_key(&data.id));
 
-    if settings.verbose {
-        println!(
-            "<Event::GameFinish> Finish game #{} -- {} vs {} -- result: {:?}",
-            data.id,
-            player_stats.get(&data.player1).unwrap().name,
-            player_stats.get(&data.player2).unwrap().name,
-            data.outcome
-        );
-    }
-
     // Update player statistics
     player_stats.get_mut(&data.player1).expect("asd").played += 1;
     player_stats.get_mut(&data.player2).expect("asd").played += 1;
+
+    // Who was at fault?
+    let culprit = |turn: &usize| {
+        if *turn == 0 {
+            data.player1
+        } else {
+            data.player2
+        }
+    };
+    match &data.aborted {
+        Some(AbortReason::Crash(turn, _)) => {
+            player_stats.get_mut(&culprit(turn)).expect("asd").crashes += 1
+        }
+        Some(AbortReason::Timeout(turn)) => {
+            player_stats.get_mut(&culprit(turn)).expect("asd").timeouts += 1
+        }
+        Some(AbortReason::IllegalMove(turn, _)) => {
+            player_stats
+                .get_mut(&culprit(turn))
+                .expect("asd")
+                .illegalmoves += 1
+        }
+        _ => {}
+    }
     match data.outcome {
         Some(GameOutcome::P1win) => {
             player_stats.get_mut(&data.player1).expect("asd").wld.w += 1;
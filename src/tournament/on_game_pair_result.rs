@@ -1,8 +1,53 @@
-use crate::tournament::{PlayerStatistics, TournamentSettings, play::GameOutcome};
+use crate::{
+    stats::WLDPairs,
+    tournament::{PlayerStatistics, play::GameOutcome},
+};
 use std::collections::HashMap;
 
+#[derive(Clone, Copy)]
+enum Score {
+    Win,
+    Loss,
+    Draw,
+}
+
+impl Score {
+    #[must_use]
+    fn opposite(self) -> Self {
+        match self {
+            Score::Win => Score::Loss,
+            Score::Loss => Score::Win,
+            Score::Draw => Score::Draw,
+        }
+    }
+}
+
+// The score of the player who moved first in the first game of the pair
+#[must_use]
+fn score(outcome: GameOutcome, is_first_game: bool) -> Score {
+    match (outcome, is_first_game) {
+        (GameOutcome::P1win, true) | (GameOutcome::P2win, false) => Score::Win,
+        (GameOutcome::P2win, true) | (GameOutcome::P1win, false) => Score::Loss,
+        (GameOutcome::Draw, _) => Score::Draw,
+    }
+}
+
+#[must_use]
+fn count(pairs: &mut WLDPairs, first: Score, second: Score) -> &mut usize {
+    match (first, second) {
+        (Score::Win, Score::Win) => &mut pairs.ww,
+        (Score::Win, Score::Loss) => &mut pairs.wl,
+        (Score::Win, Score::Draw) => &mut pairs.wd,
+        (Score::Loss, Score::Win) => &mut pairs.lw,
+        (Score::Loss, Score::Loss) => &mut pairs.ll,
+        (Score::Loss, Score::Draw) => &mut pairs.ld,
+        (Score::Draw, Score::Win) => &mut pairs.dw,
+        (Score::Draw, Score::Loss) => &mut pairs.dl,
+        (Score::Draw, Score::Draw) => &mut pairs.dd,
+    }
+}
+
 pub fn on_game_pair_result(
-    settings: &TournamentSettings,
     player_stats: &mut HashMap<usize, PlayerStatistics>,
     p1: usize,
     p2: usize,
@@ -11,35 +56,66 @@ pub fn on_game_pair_result(
 ) {
     debug_assert_ne!(p1, p2);
 
-    if settings.verbose {
-        println!("<Event::GamePairResult> Pair complete");
+    if let (Some(r1), Some(r2)) = (r1, r2) {
+        let first = score(r1, true);
+        let second = score(r2, false);
+        *count(
+            &mut player_stats.get_mut(&p1).unwrap().wld_pairs,
+            first,
+            second,
+        ) += 1;
+        *count(
+            &mut player_stats.get_mut(&p2).unwrap().wld_pairs,
+            first.opposite(),
+            second.opposite(),
+        ) += 1;
     }
+}
 
-    match (r1, r2) {
-        (Some(GameOutcome::P1win), Some(GameOutcome::P1win)) => {
-            player_stats.get_mut(&p1).unwrap().wld_pairs.wl += 1;
-            player_stats.get_mut(&p2).unwrap().wld_pairs.lw += 1;
-        }
-        (Some(GameOutcome::P1win), Some(GameOutcome::P2win)) => {
-            player_stats.get_mut(&p1).unwrap().wld_pairs.ww += 1;
-            player_stats.get_mut(&p2).unwrap().wld_pairs.ll += 1;
-        }
-        (Some(GameOutcome::P1win), Some(GameOutcome::Draw)) => todo!("Uh oh 1"),
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        (Some(GameOutcome::P2win), Some(GameOutcome::P1win)) => {
-            player_stats.get_mut(&p1).unwrap().wld_pairs.ll += 1;
-            player_stats.get_mut(&p2).unwrap().wld_pairs.ww += 1;
-        }
-        (Some(GameOutcome::P2win), Some(GameOutcome::P2win)) => {
-            player_stats.get_mut(&p1).unwrap().wld_pairs.lw += 1;
-            player_stats.get_mut(&p2).unwrap().wld_pairs.wl += 1;
-        }
-        (Some(GameOutcome::P2win), Some(GameOutcome::Draw)) => todo!("Uh oh 2"),
+    #[test]
+    fn pairs() {
+        let tests = [
+            ((GameOutcome::P1win, GameOutcome::P1win), ("wl", "lw")),
+            ((GameOutcome::P1win, GameOutcome::P2win), ("ww", "ll")),
+            ((GameOutcome::P1win, GameOutcome::Draw), ("wd", "ld")),
+            ((GameOutcome::P2win, GameOutcome::P1win), ("ll", "ww")),
+            ((GameOutcome::P2win, GameOutcome::P2win), ("lw", "wl")),
+            ((GameOutcome::P2win, GameOutcome::Draw), ("ld", "wd")),
+            ((GameOutcome::Draw, GameOutcome::P1win), ("dl", "dw")),
+            ((GameOutcome::Draw, GameOutcome::P2win), ("dw", "dl")),
+            ((GameOutcome::Draw, GameOutcome::Draw), ("dd", "dd")),
+        ];
+
+        let get = |pairs: &WLDPairs, name: &str| match name {
+            "ww" => pairs.ww,
+            "wl" => pairs.wl,
+            "wd" => pairs.wd,
+            "lw" => pairs.lw,
+            "ll" => pairs.ll,
+            "ld" => pairs.ld,
+            "dw" => pairs.dw,
+            "dl" => pairs.dl,
+            "dd" => pairs.dd,
+            _ => unreachable!(),
+        };
 
-        (Some(GameOutcome::Draw), Some(GameOutcome::P1win)) => todo!("Uh oh 3"),
-        (Some(GameOutcome::Draw), Some(GameOutcome::P2win)) => todo!("Uh oh 4"),
-        (Some(GameOutcome::Draw), Some(GameOutcome::Draw)) => todo!("Uh oh 5"),
+        for ((r1, r2), (expected1, expected2)) in tests {
+            let mut player_stats = HashMap::new();
+            player_stats.insert(0, PlayerStatistics::default());
+            player_stats.insert(1, PlayerStatistics::default());
 
-        (_, _) => {}
+            on_game_pair_result(&mut player_stats, 0, 1, Some(r1), Some(r2));
+
+            let pairs1 = &player_stats.get(&0).unwrap().wld_pairs;
+            let pairs2 = &player_stats.get(&1).unwrap().wld_pairs;
+            assert_eq!(get(pairs1, expected1), 1);
+            assert_eq!(get(pairs2, expected2), 1);
+            assert_eq!(pairs1.played(), 2);
+            assert_eq!(pairs2.played(), 2);
+        }
     }
 }
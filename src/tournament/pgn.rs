@@ -0,0 +1,174 @@
+use crate::tournament::{GameData, TournamentSettings, observer::Observer, play::GameOutcome};
+use std::{fs::OpenOptions, io::Write};
+
+#[must_use]
+fn result_string(outcome: Option<GameOutcome>) -> &'static str {
+    match outcome {
+        Some(GameOutcome::P1win) => "1-0",
+        Some(GameOutcome::P2win) => "0-1",
+        Some(GameOutcome::Draw) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+#[must_use]
+pub fn to_pgn(data: &GameData, settings: &TournamentSettings) -> String {
+    let result = result_string(data.outcome);
+    let mut pgn = String::new();
+
+    // Tags
+    pgn += "[Event \"CuterGames\"]\n";
+    pgn += &format!("[Variant \"{}\"]\n", settings.game);
+    pgn += &format!("[Round \"{}\"]\n", data.id + 1);
+    pgn += &format!("[White \"{}\"]\n", settings.players[data.player1].name);
+    pgn += &format!("[Black \"{}\"]\n", settings.players[data.player2].name);
+    pgn += &format!("[Result \"{}\"]\n", result);
+    if data.fen != "startpos" {
+        pgn += &format!("[FEN \"{}\"]\n", data.fen);
+    }
+    pgn += &format!("[PlyCount \"{}\"]\n", data.ply);
+    let white = settings.players[data.player1].limits().describe();
+    let black = settings.players[data.player2].limits().describe();
+    if white == black {
+        pgn += &format!("[TimeControl \"{}\"]\n", white);
+    } else {
+        pgn += &format!("[WhiteTimeControl \"{}\"]\n", white);
+        pgn += &format!("[BlackTimeControl \"{}\"]\n", black);
+    }
+    pgn += "\n";
+
+    // Moves
+    for (idx, mv) in data.moves.iter().enumerate() {
+        if idx % 2 == 0 {
+            pgn += &format!("{}. ", idx / 2 + 1);
+        }
+        pgn += &format!("{} ", mv);
+        // Thinking time in seconds
+        if let Some(timing) = data.timings.get(idx) {
+            pgn += &format!("{{{:.3}s}} ", timing.thinking as f64 / 1000.0);
+        }
+    }
+    pgn += result;
+    pgn += "\n\n";
+
+    pgn
+}
+
+pub fn write_game(path: &str, data: &GameData, settings: &TournamentSettings) {
+    let file = OpenOptions::new().create(true).append(true).open(path);
+    match file {
+        Ok(mut file) => {
+            if let Err(err) = file.write_all(to_pgn(data, settings).as_bytes()) {
+                println!("<Error> Failed to write PGN {}: {}", path, err);
+            }
+        }
+        Err(err) => println!("<Error> Failed to open PGN {}: {}", path, err),
+    }
+}
+
+pub struct PgnWriter {
+    path: String,
+    settings: TournamentSettings,
+}
+
+impl PgnWriter {
+    pub fn new(path: &str, settings: &TournamentSettings) -> Self {
+        Self {
+            path: path.to_string(),
+            settings: settings.clone(),
+        }
+    }
+}
+
+impl Observer for PgnWriter {
+    fn on_game_finish(&mut self, data: &GameData) {
+        write_game(&self.path, data, &self.settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tournament::{MoveTiming, PlayerSettings};
+
+    #[test]
+    fn success() {
+        let settings = TournamentSettings {
+            players: vec![
+                PlayerSettings {
+                    name: "Player 1".to_string(),
+                    ..Default::default()
+                },
+                PlayerSettings {
+                    name: "Player 2".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let data = GameData {
+            id: 3,
+            outcome: Some(GameOutcome::P2win),
+            player1: 1,
+            player2: 0,
+            ply: 3,
+            fen: "x5o/7/7/7/7/7/o5x x 0 1".to_string(),
+            moves: vec!["f1".to_string(), "b6".to_string(), "g3".to_string()],
+            timings: vec![],
+            aborted: None,
+        };
+
+        assert_eq!(
+            to_pgn(&data, &settings),
+            "[Event \"CuterGames\"]\n\
+             [Variant \"ataxx\"]\n\
+             [Round \"4\"]\n\
+             [White \"Player 2\"]\n\
+             [Black \"Player 1\"]\n\
+             [Result \"0-1\"]\n\
+             [FEN \"x5o/7/7/7/7/7/o5x x 0 1\"]\n\
+             [PlyCount \"3\"]\n\
+             [TimeControl \"0.01/move\"]\n\
+             \n\
+             1. f1 b6 2. g3 0-1\n\n"
+        );
+    }
+
+    #[test]
+    fn timings() {
+        let settings = TournamentSettings {
+            players: vec![
+                PlayerSettings {
+                    depth: Some(8),
+                    ..Default::default()
+                },
+                PlayerSettings {
+                    movetime: Some(500),
+                    nodes: Some(1000),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let timing = |thinking| MoveTiming {
+            thinking,
+            latency: 1,
+            reported: None,
+        };
+        let data = GameData {
+            id: 0,
+            outcome: None,
+            player1: 0,
+            player2: 1,
+            ply: 3,
+            fen: "startpos".to_string(),
+            moves: vec!["f1".to_string(), "b6".to_string(), "g3".to_string()],
+            timings: vec![timing(12), timing(1500), timing(0)],
+            aborted: None,
+        };
+
+        assert!(
+            to_pgn(&data, &settings).ends_with("1. f1 {0.012s} b6 {1.500s} 2. g3 {0.000s} *\n\n")
+        );
+    }
+}
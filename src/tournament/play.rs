@@ -1,16 +1,24 @@
-use crate::players::Player;
-use crate::players::clock::ClockType;
-use crate::tournament::GameData;
-use std::time::Instant;
+use crate::players::{self, Player};
+use crate::tournament::{GameData, TimingSettings};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, channel};
+use std::thread;
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, Copy)]
+// How long past its time a player can go without moving before it's killed
+const STALL_TIME: u128 = 2000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum GameOutcome {
     P1win,
     P2win,
     Draw,
 }
 
-enum AbortReason {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AbortReason {
+    Crash(usize, Vec<String>),
     Timeout(usize),
     IllegalMove(usize, String),
     MaxGameLength,
@@ -18,6 +26,33 @@ enum AbortReason {
     PlayerStall,
 }
 
+// How long a move took in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveTiming {
+    // Wall time from go to bestmove
+    pub thinking: u128,
+    // Round trip to the engine and back, measured with isready before the go. Only recorded, for
+    // telling a slow connection from a slow engine, it isn't charged or taken off the thinking time
+    pub latency: u128,
+    // What the engine said it used, if anything
+    pub reported: Option<u128>,
+}
+
+// The time a move is charged, engines are only believed when their own time fits what was measured
+#[must_use]
+fn charged_time(timing: &MoveTiming, settings: &TimingSettings) -> u128 {
+    match timing.reported {
+        Some(reported)
+            if settings.trust_engine
+                && reported <= timing.thinking
+                && reported + settings.margin >= timing.thinking =>
+        {
+            reported
+        }
+        _ => timing.thinking,
+    }
+}
+
 #[must_use]
 pub fn play(
     game_id: usize,
@@ -25,6 +60,8 @@ pub fn play(
     p2_id: usize,
     fen: &str,
     players: &mut Vec<Box<dyn Player>>,
+    timing: &TimingSettings,
+    should_abort: &AtomicBool,
 ) -> Option<GameData> {
     debug_assert!(players.len() > 1);
 
@@ -32,6 +69,8 @@ pub fn play(
     let mut result = None;
     let mut num_ply = 0;
     let mut aborted = None;
+    let mut moves = vec![];
+    let mut timings = vec![];
 
     // Set initial positions
     for player in players.iter_mut() {
@@ -39,6 +78,11 @@ pub fn play(
     }
 
     loop {
+        // The game won't be used
+        if should_abort.load(Ordering::Relaxed) {
+            return None;
+        }
+
         // Get our judge
         if players
             .get_mut(0)
@@ -56,22 +100,109 @@ pub fn play(
             break;
         };
 
+        // Engines are told both sides' time
+        let clocks = [0, 1].map(|idx| {
+            players
+                .get_mut(idx)
+                .expect("Can't find player")
+                .get_clock()
+                .clone()
+        });
+
+        // The other side thinks on our time
+        players
+            .get_mut(1 - turn)
+            .expect("Can't find other player")
+            .ponder(&clocks);
+
         // isready
+        let ready = Instant::now();
         players
             .get_mut(turn)
             .expect("Can't find current player")
             .isready();
+        let latency = ready.elapsed().as_millis();
 
         let start = Instant::now();
 
+        // Players that go far past their time are killed by the watchdog
+        let player = players.get_mut(turn).expect("Can't find current player");
+        let stall_limit = match clocks[turn].allowance() {
+            Some(allowance) => allowance + timing.margin + STALL_TIME,
+            None => timing.stall_time,
+        };
+        let pid = player.get_pid();
+        let is_stalled = AtomicBool::new(false);
+
         // Get move from current player
-        let mv = players
-            .get_mut(turn)
-            .expect("Can't find current player")
-            .get_move()
-            .expect("No move string returned from engine");
+        let mv = thread::scope(|scope| {
+            let (done, wait) = channel::<()>();
+            if let Some(pid) = pid {
+                let is_stalled = &is_stalled;
+                scope.spawn(move || {
+                    let limit = Duration::from_millis(stall_limit as u64);
+                    if wait.recv_timeout(limit) == Err(RecvTimeoutError::Timeout) {
+                        is_stalled.store(true, Ordering::Relaxed);
+                        players::kill(pid);
+                    }
+                });
+            }
+            let mv = player.get_move(&clocks);
+            drop(done);
+            mv
+        });
+
+        // Abort - No move, the engine was killed or crashed
+        let mv = match mv {
+            Some(mv) => mv,
+            None if should_abort.load(Ordering::Relaxed) => return None,
+            None if is_stalled.load(Ordering::Relaxed) => {
+                if turn == 0 {
+                    result = Some(GameOutcome::P2win);
+                } else {
+                    result = Some(GameOutcome::P1win);
+                }
+                aborted = Some(AbortReason::PlayerStall);
+                break;
+            }
+            // Players without a process to kill, such as people at the terminal, stop waiting when their time's up
+            None if pid.is_none()
+                && clocks[turn]
+                    .allowance()
+                    .is_some_and(|allowance| start.elapsed().as_millis() >= allowance) =>
+            {
+                if turn == 0 {
+                    result = Some(GameOutcome::P2win);
+                } else {
+                    result = Some(GameOutcome::P1win);
+                }
+                aborted = Some(AbortReason::Timeout(turn));
+                break;
+            }
+            None => {
+                if turn == 0 {
+                    result = Some(GameOutcome::P2win);
+                } else {
+                    result = Some(GameOutcome::P1win);
+                }
+                let stderr = players
+                    .get_mut(turn)
+                    .expect("Can't find current player")
+                    .get_stderr();
+                aborted = Some(AbortReason::Crash(turn, stderr));
+                break;
+            }
+        };
 
-        let elapsed = start.elapsed();
+        let move_timing = MoveTiming {
+            thinking: start.elapsed().as_millis(),
+            latency,
+            reported: players
+                .get_mut(turn)
+                .expect("Can't find current player")
+                .get_reported_time(),
+        };
+        let charged = charged_time(&move_timing, timing);
 
         // Legal move?
         let is_legal = players
@@ -85,19 +216,7 @@ pub fn play(
             .get_clock();
 
         // Ran out of time?
-        let is_timeout = match clock {
-            ClockType::Movetime(ms) => elapsed.as_millis() > *ms + 10,
-            ClockType::Depth(_) => false,
-            ClockType::Time(remaining, increment) => {
-                if elapsed.as_millis() >= *remaining {
-                    true
-                } else {
-                    *remaining -= elapsed.as_millis();
-                    *remaining += *increment;
-                    false
-                }
-            }
-        };
+        let is_timeout = !clock.charge(charged, timing.margin);
 
         // Abort - Illegal move
         if !is_legal {
@@ -106,7 +225,7 @@ pub fn play(
             } else {
                 result = Some(GameOutcome::P1win);
             }
-            aborted = Some(AbortReason::IllegalMove(0, mv));
+            aborted = Some(AbortReason::IllegalMove(turn, mv));
             break;
         }
 
@@ -134,78 +253,73 @@ pub fn play(
         for player in players.iter_mut() {
             player.makemove(&mv);
         }
+
+        moves.push(mv);
+        timings.push(move_timing);
     }
 
     // Was the game aborted?
-    match aborted {
+    match &aborted {
         // Result exists due to forfeit
-        Some(AbortReason::Timeout(_id)) => {
-            return Some(GameData {
-                id: 0,
-                outcome: result,
-                player1: 0,
-                player2: 1,
-                ply: num_ply,
-            });
-        }
-        Some(AbortReason::IllegalMove(_id, _movestr)) => {
-            return Some(GameData {
-                id: 0,
-                outcome: result,
-                player1: 0,
-                player2: 1,
-                ply: num_ply,
-            });
-        }
-        Some(AbortReason::PlayerStall) => {
-            return Some(GameData {
-                id: 0,
-                outcome: result,
-                player1: 0,
-                player2: 1,
-                ply: num_ply,
-            });
-        }
-        Some(AbortReason::MaxGameLength) => {
-            return Some(GameData {
-                id: 0,
-                outcome: None,
-                player1: 0,
-                player2: 1,
-                ply: num_ply,
-            });
-        }
+        Some(AbortReason::Crash(_id, _stderr)) => {}
+        Some(AbortReason::Timeout(_id)) => {}
+        Some(AbortReason::IllegalMove(_id, _movestr)) => {}
+        Some(AbortReason::PlayerStall) => {}
         // No result
-        Some(AbortReason::NoTurn) => {
-            return Some(GameData {
-                id: 0,
-                outcome: None,
-                player1: 0,
-                player2: 1,
-                ply: num_ply,
-            });
+        Some(AbortReason::MaxGameLength) | Some(AbortReason::NoTurn) => result = None,
+        // Ask the judge for the result
+        None => {
+            let asd = players
+                .get_mut(0)
+                .expect("Couldn't find judge")
+                .query_result()
+                .unwrap();
+            result = match asd.as_str() {
+                "p1win" => Some(GameOutcome::P1win),
+                "p2win" => Some(GameOutcome::P2win),
+                "draw" => Some(GameOutcome::Draw),
+                _ => None,
+            };
         }
-        None => {}
     }
 
-    // Ask the judge for the result
-    let asd = players
-        .get_mut(0)
-        .expect("Couldn't find judge")
-        .query_result()
-        .unwrap();
-    result = match asd.as_str() {
-        "p1win" => Some(GameOutcome::P1win),
-        "p2win" => Some(GameOutcome::P2win),
-        "draw" => Some(GameOutcome::Draw),
-        _ => None,
-    };
-
     Some(GameData {
         id: game_id,
         outcome: result,
         player1: p1_id,
         player2: p2_id,
         ply: num_ply,
+        fen: fen.to_string(),
+        moves,
+        timings,
+        aborted,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charged() {
+        let timing = |thinking, reported| MoveTiming {
+            thinking,
+            latency: 4,
+            reported,
+        };
+        let measured = TimingSettings::default();
+        let trusted = TimingSettings {
+            trust_engine: true,
+            ..Default::default()
+        };
+
+        assert_eq!(charged_time(&timing(100, Some(95)), &measured), 100);
+        assert_eq!(charged_time(&timing(100, None), &trusted), 100);
+        assert_eq!(charged_time(&timing(100, Some(95)), &trusted), 95);
+        assert_eq!(charged_time(&timing(100, Some(90)), &trusted), 90);
+        assert_eq!(charged_time(&timing(100, Some(100)), &trusted), 100);
+        // Claiming more than the measured time, or far less
+        assert_eq!(charged_time(&timing(100, Some(101)), &trusted), 100);
+        assert_eq!(charged_time(&timing(100, Some(89)), &trusted), 100);
+    }
+}
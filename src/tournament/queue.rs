@@ -0,0 +1,249 @@
+use crate::tournament::{
+    Affinity, Command, Event, QueueSettings, Schedule,
+    affinity::slot_cores,
+    results::Results,
+    run::{run_with, spawn_input},
+};
+use std::{
+    ops::Range,
+    sync::mpsc::{RecvTimeoutError, Sender, channel},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+struct ActiveTest {
+    idx: usize,
+    num_threads: usize,
+    // The workers it was given cores for when it started
+    slots: Range<usize>,
+    send: Sender<Event>,
+    handle: JoinHandle<Result<Results, String>>,
+}
+
+// Everyone gets their share rounded down and at least one thread while there are enough to go round, what's
+// left goes to the largest remainders
+#[must_use]
+fn shares(num_threads: usize, weights: &[usize]) -> Vec<usize> {
+    let total = weights.iter().sum::<usize>().max(1);
+    let mut shares = weights
+        .iter()
+        .map(|weight| (num_threads * weight / total).max(1))
+        .collect::<Vec<_>>();
+
+    let mut order = (0..weights.len()).collect::<Vec<_>>();
+    order.sort_by_key(|idx| std::cmp::Reverse(num_threads * weights[*idx] % total));
+    let mut left = num_threads.saturating_sub(shares.iter().sum());
+    for idx in order.iter().cycle() {
+        if left == 0 {
+            break;
+        }
+        shares[*idx] += 1;
+        left -= 1;
+    }
+
+    // Rounding up to one can hand out too many, take them back from the largest shares then the last tests
+    while shares.iter().sum::<usize>() > num_threads {
+        let idx = match shares
+            .iter()
+            .enumerate()
+            .filter(|(_, share)| **share > 1)
+            .max_by_key(|(_, share)| **share)
+        {
+            Some((idx, _)) => idx,
+            None => shares
+                .iter()
+                .rposition(|share| *share > 0)
+                .unwrap_or_default(),
+        };
+        shares[idx] -= 1;
+    }
+
+    shares
+}
+
+// The first run of workers no running test has, so a new test gets cores of its own
+#[must_use]
+fn free_slots(taken: &[Range<usize>], count: usize) -> Range<usize> {
+    let mut first = 0;
+    while let Some(slots) = taken
+        .iter()
+        .find(|slots| first < slots.end && slots.start < first + count)
+    {
+        first = slots.end;
+    }
+    first..first + count
+}
+
+// Play every test in the queue, sharing the threads between the ones running at the same time
+pub fn run_queue(queue: &QueueSettings) -> Result<Vec<Result<Results, String>>, String> {
+    let (send, recv) = channel();
+    if queue.interactive {
+        spawn_input(&send, true)?;
+    }
+
+    let mut order = (0..queue.tests.len()).collect::<Vec<_>>();
+    if queue.schedule == Schedule::Priority {
+        order.sort_by_key(|idx| std::cmp::Reverse(queue.tests[*idx].priority));
+    }
+    let mut order = order.into_iter();
+
+    let mut results = queue.tests.iter().map(|_| None).collect::<Vec<_>>();
+    let mut active: Vec<ActiveTest> = vec![];
+    let mut num_threads = queue.num_threads;
+    let mut is_stopping = false;
+
+    loop {
+        // Start the next tests while there's room
+        let mut starting = vec![];
+        while !is_stopping && active.len() + starting.len() < queue.active.min(num_threads) {
+            let Some(idx) = order.next() else {
+                break;
+            };
+            starting.push(idx);
+        }
+
+        if active.is_empty() && starting.is_empty() {
+            break;
+        }
+
+        // Share the threads out again whenever the tests change
+        let weights = active
+            .iter()
+            .map(|test| test.idx)
+            .chain(starting.iter().copied())
+            .map(|idx| match queue.schedule {
+                Schedule::RoundRobin => 1,
+                Schedule::Priority => queue.tests[idx].priority,
+            })
+            .collect::<Vec<_>>();
+        let mut thread_shares = shares(num_threads, &weights).into_iter();
+
+        for (test, share) in active.iter_mut().zip(thread_shares.by_ref()) {
+            if test.num_threads != share {
+                test.num_threads = share;
+                let _ = test.send.send(Event::Command(Command::Threads(share)));
+            }
+        }
+
+        for (idx, share) in starting.into_iter().zip(thread_shares) {
+            let test = &queue.tests[idx];
+            println!("Starting test {} on {} threads", test.name, share);
+
+            let mut settings = test.settings.clone();
+            settings.num_threads = share;
+            settings.interactive = queue.interactive;
+            let taken = active
+                .iter()
+                .map(|test| test.slots.clone())
+                .collect::<Vec<_>>();
+            let slots = free_slots(&taken, share);
+            if let Some(affinity) = &settings.affinity {
+                settings.affinity = Some(Affinity::Cores(slot_cores(
+                    affinity,
+                    num_threads,
+                    slots.clone(),
+                )));
+            }
+
+            let label = test.name.clone();
+            let (nsend, nrecv) = channel();
+            let tsend = nsend.clone();
+            active.push(ActiveTest {
+                idx,
+                num_threads: share,
+                slots,
+                send: nsend,
+                handle: thread::spawn(move || {
+                    run_with(&settings, Some(&label), vec![], tsend, nrecv)
+                }),
+            });
+        }
+
+        // Wait for a command or a test to finish
+        let mut has_changed = false;
+        while !has_changed {
+            match recv.recv_timeout(Duration::from_millis(100)) {
+                Ok(Event::Command(command)) => {
+                    match command {
+                        // Every running test needs a thread, the others wait for one to finish
+                        Command::Threads(n) if n < active.len() => {
+                            println!(
+                                "<Error> {} tests are running, threads must be at least that",
+                                active.len()
+                            );
+                            continue;
+                        }
+                        Command::Threads(n) => {
+                            num_threads = n;
+                            has_changed = true;
+                            continue;
+                        }
+                        Command::Stop | Command::Abort => is_stopping = true,
+                        _ => {}
+                    }
+                    for test in &active {
+                        let _ = test.send.send(Event::Command(command.clone()));
+                    }
+                }
+                Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            let mut idx = 0;
+            while idx < active.len() {
+                if !active[idx].handle.is_finished() {
+                    idx += 1;
+                    continue;
+                }
+
+                let test = active.remove(idx);
+                let result = test
+                    .handle
+                    .join()
+                    .unwrap_or_else(|_| Err("Test panicked".to_string()));
+                match &result {
+                    Ok(_) => println!("Finished test {}", queue.tests[test.idx].name),
+                    Err(err) => println!(
+                        "<Error> Test {} failed: {}",
+                        queue.tests[test.idx].name, err
+                    ),
+                }
+                results[test.idx] = Some(result);
+                has_changed = true;
+            }
+        }
+    }
+
+    // Tests never started after a stop
+    Ok(results
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err("Not played".to_string())))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_shares() {
+        assert_eq!(shares(8, &[1, 1]), vec![4, 4]);
+        assert_eq!(shares(7, &[1, 1, 1]), vec![3, 2, 2]);
+        assert_eq!(shares(8, &[1, 3]), vec![2, 6]);
+        assert_eq!(shares(5, &[1, 3]), vec![1, 4]);
+        assert_eq!(shares(10, &[2, 1, 1]), vec![5, 3, 2]);
+        // Never less than one each while there are enough
+        assert_eq!(shares(4, &[1, 100]), vec![1, 3]);
+        assert_eq!(shares(3, &[1, 1, 100]), vec![1, 1, 1]);
+        // Never more than there are
+        assert_eq!(shares(2, &[1, 1, 1]), vec![1, 1, 0]);
+    }
+
+    #[test]
+    fn slots() {
+        assert_eq!(free_slots(&[], 3), 0..3);
+        assert_eq!(free_slots(&[0..2, 4..6], 2), 2..4);
+        assert_eq!(free_slots(&[0..2, 4..6], 3), 6..9);
+        assert_eq!(free_slots(&[2..4, 6..8], 2), 0..2);
+    }
+}
@@ -0,0 +1,663 @@
+use crate::tournament::{
+    Controls, Event, GameData, MAX_THREADS, PlayerSettings, TimingSettings, TournamentSettings,
+    affinity::worker_cores,
+    generator::{Generator, Work},
+    play::play,
+    spsa::Tuner,
+    worker::setup_players,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::create_dir_all,
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// Remote workers are numbered after the listener, above any local thread
+pub(super) const LISTENER_ID: usize = MAX_THREADS;
+
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+const WORKER_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// One JSON object per line
+#[derive(Serialize, Deserialize)]
+enum Message {
+    // Coordinator to worker
+    Hello {
+        version: String,
+        game: String,
+        players: Vec<PlayerSettings>,
+        fens: Vec<String>,
+        timing: TimingSettings,
+    },
+    Work(Work, [Vec<(String, String)>; 2]),
+    Done,
+    // Worker to coordinator
+    Started(usize),
+    Unsupported(usize),
+    Finished(GameData),
+    // Either way, whenever there's nothing else to say
+    Ping,
+}
+
+// A bare port only takes workers on this machine, other interfaces have to be asked for by address
+#[must_use]
+pub(super) fn listen_address(address: &str) -> String {
+    match address.parse::<u16>() {
+        Ok(port) => format!("127.0.0.1:{}", port),
+        Err(_) => address.to_string(),
+    }
+}
+
+// Workers are trusted to report the game they were given, anything else loses them
+#[must_use]
+fn is_expected(data: &GameData, work: &Work) -> bool {
+    data.id == work.game_id && data.player1 == work.player1 && data.player2 == work.player2
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: Arc<Mutex<TcpStream>>,
+    line: String,
+}
+
+fn write_message(writer: &Mutex<TcpStream>, message: &Message) -> io::Result<()> {
+    let mut text = serde_json::to_string(message)?;
+    text.push('\n');
+    writer
+        .lock()
+        .expect("Lock fail thingy")
+        .write_all(text.as_bytes())
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        let writer = stream.try_clone()?;
+        Ok(Self {
+            reader: BufReader::new(stream),
+            writer: Arc::new(Mutex::new(writer)),
+            line: String::new(),
+        })
+    }
+
+    fn send(&self, message: &Message) -> io::Result<()> {
+        write_message(&self.writer, message)
+    }
+
+    // Anything read before a timeout is kept for the next call
+    fn recv(&mut self) -> io::Result<Message> {
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "connection closed",
+            ));
+        }
+        let message = serde_json::from_str(&self.line);
+        self.line.clear();
+        Ok(message?)
+    }
+
+    fn shutdown(&self) {
+        let _ = self
+            .writer
+            .lock()
+            .expect("Lock fail thingy")
+            .shutdown(Shutdown::Both);
+    }
+}
+
+enum WaitError {
+    Aborted,
+    Lost(io::Error),
+}
+
+// Wait for the worker's next message, it pings while playing so silence means it's gone
+fn wait(connection: &mut Connection, controls: &Controls) -> Result<Message, WaitError> {
+    let mut last_heard = Instant::now();
+    loop {
+        if controls.should_abort.load(Ordering::Relaxed) {
+            return Err(WaitError::Aborted);
+        }
+
+        match connection.recv() {
+            Ok(Message::Ping) => last_heard = Instant::now(),
+            Ok(message) => return Ok(message),
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                if last_heard.elapsed() > WORKER_TIMEOUT {
+                    return Err(WaitError::Lost(io::Error::new(
+                        ErrorKind::TimedOut,
+                        "no response",
+                    )));
+                }
+            }
+            Err(err) => return Err(WaitError::Lost(err)),
+        }
+    }
+}
+
+// Hand out games to a remote worker the same way a local thread would play them
+fn serve(
+    mut connection: Connection,
+    thread_id: usize,
+    generator: Arc<Mutex<Generator>>,
+    send: Sender<Event>,
+    settings: &TournamentSettings,
+    controls: Arc<Controls>,
+    tuner: Option<Arc<Mutex<Tuner>>>,
+) {
+    let hello = Message::Hello {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        game: settings.game.clone(),
+        players: settings.players.clone(),
+        fens: settings.fens.clone(),
+        timing: settings.timing,
+    };
+    if connection.send(&hello).is_err() {
+        let _ = send.send(Event::ThreadFinish(thread_id));
+        return;
+    }
+
+    // The worker hears from us at least this often while it waits for a game
+    let mut last_sent = Instant::now();
+    let mut keep_alive = |connection: &Connection| {
+        if last_sent.elapsed() < PING_INTERVAL {
+            return true;
+        }
+        last_sent = Instant::now();
+        connection.send(&Message::Ping).is_ok()
+    };
+
+    while !controls.should_stop.load(Ordering::Relaxed) {
+        if controls.is_paused.load(Ordering::Relaxed) {
+            if generator.lock().expect("Lock fail thingy").is_finished() || !keep_alive(&connection)
+            {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+            continue;
+        }
+
+        let (work, is_pending) = {
+            let mut generator = generator.lock().expect("Lock fail thingy");
+            let work = generator.next();
+            (work, !generator.in_progress.is_empty())
+        };
+        let Some(work) = work else {
+            // Games being played elsewhere are handed back if their worker is lost
+            if is_pending && keep_alive(&connection) {
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+            break;
+        };
+
+        let options = [work.player1, work.player2].map(|id| {
+            tuner.as_ref().map_or(vec![], |tuner| {
+                tuner
+                    .lock()
+                    .expect("Lock fail thingy")
+                    .options(work.game_id, id)
+            })
+        });
+
+        let mut is_started = false;
+        let mut result = connection
+            .send(&Message::Work(work.clone(), options))
+            .map_err(WaitError::Lost)
+            .and_then(|_| wait(&mut connection, &controls));
+
+        if let Ok(Message::Started(id)) = result
+            && id == work.game_id
+        {
+            is_started = true;
+            let _ = send.send(Event::PlayerCreate(work.player1));
+            let _ = send.send(Event::PlayerCreate(work.player2));
+            let _ = send.send(Event::GameStart(
+                work.game_id,
+                work.fen_idx,
+                work.player1,
+                work.player2,
+                thread_id,
+            ));
+            result = wait(&mut connection, &controls);
+        }
+
+        let is_lost = match result {
+            Ok(Message::Finished(data)) if is_started && is_expected(&data, &work) => {
+                let _ = send.send(Event::GameFinish(data));
+                false
+            }
+            // No point continuing the tournament
+            Ok(Message::Unsupported(id))
+                if !is_started && (id == work.player1 || id == work.player2) =>
+            {
+                println!(
+                    "<Error> Player {} does not support game {} on worker {}",
+                    settings.players[id].name, settings.game, thread_id
+                );
+                controls.should_stop.store(true, Ordering::Relaxed);
+                false
+            }
+            Ok(_) => {
+                println!("<Error> Unexpected message from worker {}", thread_id);
+                true
+            }
+            // Aborted games are left unfinished so they can be played again with --resume
+            Err(WaitError::Aborted) => false,
+            Err(WaitError::Lost(err)) => {
+                println!("<Error> Lost worker {}: {}", thread_id, err);
+                true
+            }
+        };
+
+        if is_started {
+            for id in [work.player1, work.player2] {
+                let _ = send.send(Event::PlayerDestroy(id));
+            }
+        }
+
+        if is_lost {
+            println!("Game {} will be played again", work.game_id);
+            generator
+                .lock()
+                .expect("Lock fail thingy")
+                .reissue(work.game_id);
+            break;
+        }
+        if controls.should_abort.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+
+    if !controls.should_abort.load(Ordering::Relaxed) {
+        let _ = connection.send(&Message::Done);
+    }
+    connection.shutdown();
+    let _ = send.send(Event::ThreadFinish(thread_id));
+}
+
+// Accept remote workers until there are no games left to give them
+pub(super) fn listen(
+    listener: TcpListener,
+    generator: Arc<Mutex<Generator>>,
+    send: Sender<Event>,
+    settings: &TournamentSettings,
+    controls: Arc<Controls>,
+    tuner: Option<Arc<Mutex<Tuner>>>,
+) {
+    let _ = send.send(Event::ThreadStart(LISTENER_ID));
+
+    let mut handles = vec![];
+    let mut next_id = LISTENER_ID + 1;
+    if let Err(err) = listener.set_nonblocking(true) {
+        println!("<Error> Failed to listen for workers: {}", err);
+        controls.should_stop.store(true, Ordering::Relaxed);
+    }
+
+    while !controls.should_stop.load(Ordering::Relaxed) {
+        {
+            let generator = generator.lock().expect("Lock fail thingy");
+            if generator.is_finished() && generator.in_progress.is_empty() {
+                break;
+            }
+        }
+
+        let (stream, address) = match listener.accept() {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                if err.kind() != ErrorKind::WouldBlock {
+                    println!("<Error> Failed to accept worker: {}", err);
+                }
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+        };
+
+        let connection = stream
+            .set_nonblocking(false)
+            .and_then(|_| stream.set_read_timeout(Some(POLL_INTERVAL)))
+            .and_then(|_| Connection::new(stream));
+        let connection = match connection {
+            Ok(connection) => connection,
+            Err(err) => {
+                println!("<Error> Failed to accept worker {}: {}", address, err);
+                continue;
+            }
+        };
+
+        println!("Worker {} connected from {}", next_id, address);
+        let _ = send.send(Event::ThreadStart(next_id));
+
+        let thread_id = next_id;
+        let nsettings = settings.clone();
+        let nsend = send.clone();
+        let ncontrols = controls.clone();
+        let ngenerator = generator.clone();
+        let ntuner = tuner.clone();
+        handles.push(thread::spawn(move || {
+            serve(
+                connection, thread_id, ngenerator, nsend, &nsettings, ncontrols, ntuner,
+            )
+        }));
+        next_id += 1;
+    }
+
+    for handle in handles {
+        let _ = handle.join();
+    }
+
+    let _ = send.send(Event::ThreadFinish(LISTENER_ID));
+}
+
+// Play the games handed out by the coordinator until it says there are no more
+fn session(stream: TcpStream, worker_id: usize, settings: &TournamentSettings) -> io::Result<()> {
+    // The coordinator pings while it has no game for us, so silence means it's gone
+    stream.set_read_timeout(Some(WORKER_TIMEOUT))?;
+    let mut connection = Connection::new(stream)?;
+
+    let Message::Hello {
+        version,
+        game,
+        players,
+        fens,
+        timing,
+    } = connection.recv()?
+    else {
+        return Err(io::Error::new(ErrorKind::InvalidData, "expected hello"));
+    };
+    if version != env!("CARGO_PKG_VERSION") {
+        println!(
+            "<Error> Coordinator is running version {}, this is {}",
+            version,
+            env!("CARGO_PKG_VERSION")
+        );
+        return Ok(());
+    }
+
+    let cpus = settings.affinity.as_ref().map_or(vec![], |affinity| {
+        worker_cores(affinity, settings.num_threads, worker_id)
+    });
+    let settings = TournamentSettings {
+        players,
+        game,
+        fens,
+        timing,
+        log: settings.log.clone(),
+        ..Default::default()
+    };
+
+    loop {
+        let (work, options) = match connection.recv()? {
+            Message::Work(work, options) => (work, options),
+            Message::Done => return Ok(()),
+            Message::Ping => continue,
+            _ => {
+                return Err(io::Error::new(ErrorKind::InvalidData, "unexpected message"));
+            }
+        };
+
+        // Keep the coordinator posted, the game is abandoned if it goes away
+        let should_abort = Arc::new(AtomicBool::new(false));
+        let is_done = Arc::new(AtomicBool::new(false));
+        let pinger = {
+            let writer = connection.writer.clone();
+            let should_abort = should_abort.clone();
+            let is_done = is_done.clone();
+            thread::spawn(move || {
+                let mut last_ping = Instant::now();
+                while !is_done.load(Ordering::Relaxed) {
+                    if last_ping.elapsed() >= PING_INTERVAL {
+                        if write_message(&writer, &Message::Ping).is_err() {
+                            should_abort.store(true, Ordering::Relaxed);
+                            break;
+                        }
+                        last_ping = Instant::now();
+                    }
+                    thread::sleep(Duration::from_millis(100));
+                }
+            })
+        };
+
+        let result = match setup_players(&work, &options, &settings, &cpus) {
+            Ok(mut players) => connection.send(&Message::Started(work.game_id)).map(|_| {
+                play(
+                    work.game_id,
+                    work.player1,
+                    work.player2,
+                    &settings.fens[work.fen_idx],
+                    &mut players,
+                    &settings.timing,
+                    &should_abort,
+                )
+            }),
+            Err(id) => connection.send(&Message::Unsupported(id)).map(|_| None),
+        };
+
+        is_done.store(true, Ordering::Relaxed);
+        let _ = pinger.join();
+
+        match result? {
+            _ if should_abort.load(Ordering::Relaxed) => {
+                return Err(io::Error::new(ErrorKind::BrokenPipe, "game abandoned"));
+            }
+            Some(data) => connection.send(&Message::Finished(data))?,
+            None => {}
+        }
+    }
+}
+
+pub fn run_worker(settings: &TournamentSettings) -> Result<(), String> {
+    let Some(address) = &settings.worker else {
+        return Err("No coordinator address given".to_string());
+    };
+    if let Some(dir) = &settings.log {
+        create_dir_all(dir)
+            .map_err(|e| format!("Failed to create log directory {}: {}", dir, e))?;
+    }
+
+    // One connection per game played simultaneously
+    let threads = (0..settings.num_threads)
+        .map(|worker_id| {
+            let address = address.clone();
+            let settings = settings.clone();
+            thread::spawn(move || {
+                loop {
+                    match TcpStream::connect(&address) {
+                        Ok(stream) => match session(stream, worker_id, &settings) {
+                            Ok(()) => break,
+                            Err(err) => println!("<Error> Lost connection to {}: {}", address, err),
+                        },
+                        Err(err) => println!("<Error> Failed to connect to {}: {}", address, err),
+                    }
+                    thread::sleep(RECONNECT_DELAY);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        let _ = thread.join();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tournament::GameOutcome;
+    use std::sync::mpsc::channel;
+
+    fn finished(work: &Work) -> Message {
+        Message::Finished(GameData {
+            id: work.game_id,
+            outcome: Some(GameOutcome::Draw),
+            player1: work.player1,
+            player2: work.player2,
+            ply: 0,
+            fen: "startpos".to_string(),
+            moves: vec![],
+            timings: vec![],
+            aborted: None,
+        })
+    }
+
+    #[test]
+    fn lost_worker() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let settings = TournamentSettings {
+            players: vec![PlayerSettings::default(), PlayerSettings::default()],
+            fens: vec!["startpos".to_string()],
+            num_games: Some(4),
+            ..Default::default()
+        };
+        let generator = Arc::new(Mutex::new(Generator::new(2, 1, Some(4))));
+        let controls = Arc::new(Controls::default());
+        let (send, recv) = channel();
+
+        let ngenerator = generator.clone();
+        let coordinator = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            stream.set_read_timeout(Some(POLL_INTERVAL)).unwrap();
+            let connection = Connection::new(stream).unwrap();
+            serve(connection, 7, ngenerator, send, &settings, controls, None);
+        });
+
+        // Finish the first game then disappear halfway through the second
+        let mut worker = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+        assert!(matches!(worker.recv().unwrap(), Message::Hello { .. }));
+        let Message::Work(work, _) = worker.recv().unwrap() else {
+            panic!("expected work");
+        };
+        assert_eq!(work.game_id, 0);
+        worker.send(&Message::Started(0)).unwrap();
+        worker.send(&Message::Ping).unwrap();
+        worker.send(&finished(&work)).unwrap();
+        let Message::Work(work, _) = worker.recv().unwrap() else {
+            panic!("expected work");
+        };
+        assert_eq!(work.game_id, 1);
+        worker.send(&Message::Started(1)).unwrap();
+        worker.shutdown();
+        coordinator.join().unwrap();
+
+        let events = recv.try_iter().collect::<Vec<_>>();
+        assert!(matches!(events[2], Event::GameStart(0, 0, 0, 1, 7)));
+        assert!(matches!(&events[3], Event::GameFinish(data) if data.id == 0));
+        assert!(matches!(events[8], Event::GameStart(1, 0, 1, 0, 7)));
+        assert!(matches!(events.last(), Some(Event::ThreadFinish(7))));
+        let creates = events
+            .iter()
+            .filter(|event| matches!(event, Event::PlayerCreate(_)))
+            .count();
+        let destroys = events
+            .iter()
+            .filter(|event| matches!(event, Event::PlayerDestroy(_)))
+            .count();
+        assert_eq!((creates, destroys), (4, 4));
+
+        // The lost game is played again, finished games are left to the coordinator
+        let generator = generator.lock().unwrap();
+        assert_eq!(generator.requeue, vec![1]);
+        assert_eq!(generator.pending(), vec![1, 0]);
+    }
+
+    // Serve one game to a worker replying with the messages given
+    fn serve_replies(replies: &[Message]) -> (Vec<Event>, Vec<usize>, bool) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let settings = TournamentSettings {
+            players: vec![PlayerSettings::default(), PlayerSettings::default()],
+            fens: vec!["startpos".to_string()],
+            num_games: Some(2),
+            ..Default::default()
+        };
+        let generator = Arc::new(Mutex::new(Generator::new(2, 1, Some(2))));
+        let controls = Arc::new(Controls::default());
+        let (send, recv) = channel();
+
+        let ngenerator = generator.clone();
+        let ncontrols = controls.clone();
+        let coordinator = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            stream.set_read_timeout(Some(POLL_INTERVAL)).unwrap();
+            let connection = Connection::new(stream).unwrap();
+            serve(connection, 7, ngenerator, send, &settings, ncontrols, None);
+        });
+
+        let mut worker = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+        assert!(matches!(worker.recv().unwrap(), Message::Hello { .. }));
+        assert!(matches!(worker.recv().unwrap(), Message::Work(..)));
+        for reply in replies {
+            worker.send(reply).unwrap();
+        }
+        coordinator.join().unwrap();
+
+        let requeue = generator.lock().unwrap().requeue.clone();
+        let events = recv.try_iter().collect::<Vec<_>>();
+        (
+            events,
+            requeue,
+            controls.should_stop.load(Ordering::Relaxed),
+        )
+    }
+
+    #[test]
+    fn bad_worker() {
+        let work = Work {
+            game_id: 0,
+            fen_idx: 0,
+            player1: 0,
+            player2: 1,
+        };
+        let other = Work {
+            game_id: 1,
+            ..work.clone()
+        };
+
+        // Players that aren't in the game
+        let (events, requeue, should_stop) = serve_replies(&[Message::Unsupported(99)]);
+        assert_eq!(requeue, vec![0]);
+        assert!(!should_stop);
+        assert!(matches!(events.last(), Some(Event::ThreadFinish(7))));
+
+        // Results for a game it wasn't given
+        let (events, requeue, _) = serve_replies(&[Message::Started(0), finished(&other)]);
+        assert_eq!(requeue, vec![0]);
+        assert!(
+            !events
+                .iter()
+                .any(|event| matches!(event, Event::GameFinish(_)))
+        );
+
+        // Results for a game it never started
+        let (events, requeue, _) = serve_replies(&[finished(&work)]);
+        assert_eq!(requeue, vec![0]);
+        assert!(
+            !events
+                .iter()
+                .any(|event| matches!(event, Event::GameFinish(_)))
+        );
+
+        // Refusing a game over one of its players is fine
+        let (_, requeue, should_stop) = serve_replies(&[Message::Unsupported(1)]);
+        assert!(requeue.is_empty());
+        assert!(should_stop);
+    }
+
+    #[test]
+    fn listen_addresses() {
+        assert_eq!(listen_address("9000"), "127.0.0.1:9000");
+        assert_eq!(listen_address("0.0.0.0:9000"), "0.0.0.0:9000");
+    }
+}
@@ -0,0 +1,328 @@
+use crate::{
+    elo::trinomial,
+    sprt,
+    tournament::{
+        PlayerStatistics, SPRTSettings, TournamentSettings, TournamentStatistics,
+        observer::Observer,
+    },
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fs::{rename, write},
+    time::Duration,
+};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Pentanomial {
+    pub ll: usize,
+    pub ld_dl: usize,
+    pub wl_lw_dd: usize,
+    pub wd_dw: usize,
+    pub ww: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PlayerResults {
+    pub id: usize,
+    pub name: String,
+    pub played: usize,
+    pub w: usize,
+    pub l: usize,
+    pub d: usize,
+    pub pentanomial: Pentanomial,
+    pub elo: f32,
+    pub elo_err: f32,
+    pub crashes: usize,
+    pub timeouts: usize,
+    pub illegal_moves: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum SPRTStatus {
+    Running,
+    H0Accepted,
+    H1Accepted,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SPRTResults {
+    pub kind: String,
+    pub elo0: f32,
+    pub elo1: f32,
+    pub alpha: f32,
+    pub beta: f32,
+    pub llr: f32,
+    pub lbound: f32,
+    pub ubound: f32,
+    pub status: SPRTStatus,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Results {
+    pub game: String,
+    pub games_completed: usize,
+    pub elapsed_seconds: f32,
+    pub games_per_second: f32,
+    pub players: Vec<PlayerResults>,
+    pub sprt: Vec<SPRTResults>,
+}
+
+#[must_use]
+fn sprt_results(kind: &str, sprt: &SPRTSettings, stats: &PlayerStatistics) -> SPRTResults {
+    let llr = sprt::get_llr(&stats.wld, sprt.elo0, sprt.elo1);
+    let lbound = sprt::get_lbound(sprt.alpha, sprt.beta);
+    let ubound = sprt::get_ubound(sprt.alpha, sprt.beta);
+    let status = if llr <= lbound {
+        SPRTStatus::H0Accepted
+    } else if llr >= ubound {
+        SPRTStatus::H1Accepted
+    } else {
+        SPRTStatus::Running
+    };
+
+    SPRTResults {
+        kind: kind.to_string(),
+        elo0: sprt.elo0,
+        elo1: sprt.elo1,
+        alpha: sprt.alpha,
+        beta: sprt.beta,
+        llr,
+        lbound,
+        ubound,
+        status,
+    }
+}
+
+#[must_use]
+pub(super) fn collect(
+    tournament_stats: &TournamentStatistics,
+    player_stats: &HashMap<usize, PlayerStatistics>,
+    settings: &TournamentSettings,
+    elapsed: Duration,
+) -> Results {
+    let mut players = vec![];
+    for id in 0..settings.players.len() {
+        let Some(stats) = player_stats.get(&id) else {
+            continue;
+        };
+        let pairs = &stats.wld_pairs;
+        let (elo, elo_err) = trinomial::elo_err(&stats.wld);
+
+        players.push(PlayerResults {
+            id,
+            name: stats.name.clone(),
+            played: stats.played,
+            w: stats.wld.w,
+            l: stats.wld.l,
+            d: stats.wld.d,
+            pentanomial: Pentanomial {
+                ll: pairs.ll,
+                ld_dl: pairs.ld + pairs.dl,
+                wl_lw_dd: pairs.wl + pairs.lw + pairs.dd,
+                wd_dw: pairs.wd + pairs.dw,
+                ww: pairs.ww,
+            },
+            elo,
+            elo_err,
+            crashes: stats.crashes,
+            timeouts: stats.timeouts,
+            illegal_moves: stats.illegalmoves,
+        });
+    }
+
+    // SPRT only makes sense between two players, from the first player's point of view
+    let mut sprt = vec![];
+    if settings.players.len() == 2
+        && let Some(stats) = player_stats.get(&0)
+    {
+        if let Some(tri) = &settings.sprt_trinomial {
+            sprt.push(sprt_results("trinomial", tri, stats));
+        }
+        if let Some(penta) = &settings.sprt_pentanomial {
+            sprt.push(sprt_results("pentanomial", penta, stats));
+        }
+    }
+
+    let elapsed_seconds = elapsed.as_secs_f32();
+    Results {
+        game: settings.game.clone(),
+        games_completed: tournament_stats.games_completed,
+        elapsed_seconds,
+        games_per_second: if elapsed_seconds > 0.0 {
+            tournament_stats.games_completed as f32 / elapsed_seconds
+        } else {
+            0.0
+        },
+        players,
+        sprt,
+    }
+}
+
+#[must_use]
+pub fn to_csv(results: &Results) -> String {
+    let mut csv = String::from(
+        "id,name,played,w,l,d,ll,ld_dl,wl_lw_dd,wd_dw,ww,elo,elo_err,crashes,timeouts,illegal_moves\n",
+    );
+
+    for player in &results.players {
+        csv += &format!(
+            "{},\"{}\",{},{},{},{},{},{},{},{},{},{:.2},{:.2},{},{},{}\n",
+            player.id,
+            player.name.replace('"', "\"\""),
+            player.played,
+            player.w,
+            player.l,
+            player.d,
+            player.pentanomial.ll,
+            player.pentanomial.ld_dl,
+            player.pentanomial.wl_lw_dd,
+            player.pentanomial.wd_dw,
+            player.pentanomial.ww,
+            player.elo,
+            player.elo_err,
+            player.crashes,
+            player.timeouts,
+            player.illegal_moves,
+        );
+    }
+
+    csv
+}
+
+// Write to a temporary file first so readers never see a half written file
+fn write_file(path: &str, contents: &str) {
+    let tmp = format!("{}.tmp", path);
+    if let Err(err) = write(&tmp, contents).and_then(|_| rename(&tmp, path)) {
+        println!("<Error> Failed to write results {}: {}", path, err);
+    }
+}
+
+pub fn write_results(results: &Results, settings: &TournamentSettings) {
+    if let Some(path) = &settings.results_json {
+        match serde_json::to_string_pretty(results) {
+            Ok(json) => write_file(path, &json),
+            Err(err) => println!("<Error> Failed to serialise results: {}", err),
+        }
+    }
+
+    if let Some(path) = &settings.results_csv {
+        write_file(path, &to_csv(results));
+    }
+}
+
+pub struct ResultsWriter {
+    settings: TournamentSettings,
+}
+
+impl ResultsWriter {
+    pub fn new(settings: &TournamentSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+}
+
+impl Observer for ResultsWriter {
+    fn on_print_update(&mut self, results: &Results) {
+        write_results(results, &self.settings);
+    }
+
+    fn on_end(&mut self, results: &Results) {
+        write_results(results, &self.settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        stats::{WLD, WLDPairs},
+        tournament::PlayerSettings,
+    };
+
+    fn get_results() -> Results {
+        let settings = TournamentSettings {
+            players: vec![PlayerSettings::default(), PlayerSettings::default()],
+            sprt_trinomial: Some(SPRTSettings {
+                alpha: 0.05,
+                beta: 0.05,
+                elo0: 0.0,
+                elo1: 10.0,
+                autostop: true,
+            }),
+            ..Default::default()
+        };
+
+        let mut player_stats = HashMap::new();
+        player_stats.insert(
+            1,
+            PlayerStatistics {
+                name: "Player \"2\"".to_string(),
+                played: 310,
+                wld: WLD {
+                    w: 61,
+                    l: 191,
+                    d: 58,
+                },
+                ..Default::default()
+            },
+        );
+        player_stats.insert(
+            0,
+            PlayerStatistics {
+                name: "Player 1".to_string(),
+                played: 310,
+                timeouts: 2,
+                wld: WLD {
+                    w: 191,
+                    l: 61,
+                    d: 58,
+                },
+                wld_pairs: WLDPairs {
+                    ww: 3,
+                    dd: 1,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+
+        collect(
+            &TournamentStatistics {
+                games_completed: 310,
+                ..Default::default()
+            },
+            &player_stats,
+            &settings,
+            Duration::from_secs(10),
+        )
+    }
+
+    #[test]
+    fn collect_results() {
+        let results = get_results();
+
+        assert_eq!(results.games_completed, 310);
+        assert!((results.games_per_second - 31.0).abs() < 0.01);
+        assert_eq!(results.players.len(), 2);
+        assert_eq!(results.players[0].id, 0);
+        assert_eq!(results.players[1].id, 1);
+        assert_eq!(results.players[0].timeouts, 2);
+        assert_eq!(results.players[0].pentanomial.ww, 3);
+        assert_eq!(results.players[0].pentanomial.wl_lw_dd, 1);
+        assert_eq!(results.sprt.len(), 1);
+        assert_eq!(results.sprt[0].status, SPRTStatus::H1Accepted);
+    }
+
+    #[test]
+    fn csv() {
+        let csv = to_csv(&get_results());
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("0,\"Player 1\",310,191,61,58,0,0,1,0,3,"));
+        assert!(lines[1].ends_with(",0,2,0"));
+        assert!(lines[2].starts_with("1,\"Player \"\"2\"\"\",310,61,191,58,"));
+    }
+}
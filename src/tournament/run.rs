@@ -1,27 +1,190 @@
 use crate::stats::{WLD, WLDPairs};
+use crate::tournament::command::{Command, parse_command};
+use crate::tournament::console::{Console, labelled};
+use crate::tournament::eventlog::EventLog;
 use crate::tournament::generator::Generator;
+use crate::tournament::http::StatusPage;
+use crate::tournament::observer::{Observer, notify};
+use crate::tournament::on_command::on_command;
 use crate::tournament::on_game_finish::on_game_finish;
 use crate::tournament::on_game_pair_result::on_game_pair_result;
-use crate::tournament::on_game_start::on_game_start;
 use crate::tournament::on_player_create::on_player_create;
 use crate::tournament::on_player_destroy::on_player_destroy;
-use crate::tournament::on_print_results::on_print_results;
-use crate::tournament::on_thread_finish::on_thread_finish;
-use crate::tournament::on_thread_start::on_thread_start;
+use crate::tournament::pgn::PgnWriter;
 use crate::tournament::play::GameOutcome;
+use crate::tournament::remote::{listen, listen_address};
+use crate::tournament::results::{self, Results, ResultsWriter};
+use crate::tournament::spsa::Tuner;
+use crate::tournament::state::{self, State, StateError};
 use crate::tournament::worker::worker;
-use crate::tournament::{Event, PlayerStatistics, TournamentSettings, TournamentStatistics};
-use std::sync::atomic::{AtomicBool, Ordering};
+use crate::tournament::{
+    Controls, Event, PlayerStatistics, TournamentSettings, TournamentStatistics,
+};
+use signal_hook::{
+    consts::{SIGINT, SIGTERM},
+    iterator::Signals,
+};
+use std::fs::create_dir_all;
+use std::net::TcpListener;
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
-use std::{collections::HashMap, sync::mpsc::channel, thread};
+use std::time::{Duration, Instant};
+use std::{
+    collections::HashMap,
+    sync::mpsc::{Receiver, Sender, channel},
+    thread,
+};
 
-pub fn run(settings: &TournamentSettings) {
+// How long games being played get to finish after Ctrl-C before they're aborted
+const GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+fn save_state(
+    path: &str,
+    settings: &TournamentSettings,
+    generator: &Mutex<Generator>,
+    tournament_stats: &TournamentStatistics,
+    player_stats: &HashMap<usize, PlayerStatistics>,
+    pair_store: &HashMap<usize, GameOutcome>,
+) {
+    let generator = generator.lock().expect("Lock fail thingy");
+    let state = State {
+        hash: state::settings_hash(settings),
+        next_id: generator.id,
+        pending: generator.pending(),
+        tournament_stats: tournament_stats.clone(),
+        player_stats: player_stats.clone(),
+        pair_store: pair_store.clone(),
+    };
+
+    if let Err(err) = state::save(path, &state) {
+        println!("<Error> Failed to save state {}: {}", path, err);
+    }
+}
+
+fn save_tuner(settings: &TournamentSettings, label: Option<&str>, tuner: &Tuner) {
+    let summary = format!("Tuning: {}", tuner.summary());
+    println!("{}", labelled(label, &summary));
+    if let Some(path) = settings.tune.as_ref().and_then(|tune| tune.output.as_ref()) {
+        tuner.write(path);
+    }
+}
+
+// The outputs selected in the settings, run before any given to run()
+fn builtin_observers(
+    settings: &TournamentSettings,
+    label: Option<&str>,
+) -> Result<Vec<Box<dyn Observer>>, String> {
+    let mut observers: Vec<Box<dyn Observer>> = vec![];
+
+    if settings.interactive {
+        observers.push(Box::new(Console::new(settings, label)));
+    }
+
+    if let Some(path) = &settings.pgn {
+        observers.push(Box::new(PgnWriter::new(path, settings)));
+    }
+    if settings.results_json.is_some() || settings.results_csv.is_some() {
+        observers.push(Box::new(ResultsWriter::new(settings)));
+    }
+    if let Some(path) = &settings.eventlog {
+        observers.push(Box::new(EventLog::new(path, settings)?));
+    }
+    if let Some(address) = &settings.http {
+        observers.push(Box::new(StatusPage::new(address, settings)?));
+    }
+
+    Ok(observers)
+}
+
+// Commands typed into the terminal, and interrupts
+pub(super) fn spawn_input(send: &Sender<Event>, has_console: bool) -> Result<(), String> {
+    // Console thread, left out when a human player is reading the terminal
+    if has_console {
+        let nsend = send.clone();
+        thread::spawn(move || {
+            loop {
+                let mut buffer = String::new();
+                match std::io::stdin().read_line(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => match parse_command(&buffer) {
+                        Ok(command) => {
+                            let _ = nsend.send(Event::Command(command));
+                        }
+                        Err(msg) => println!("{}, try help", msg),
+                    },
+                }
+            }
+        });
+    }
+
+    // Signal thread
+    let mut signals = Signals::new([SIGINT, SIGTERM])
+        .map_err(|e| format!("Failed to register signal handlers: {}", e))?;
+    let nsend = send.clone();
+    thread::spawn(move || {
+        let mut num_signals = 0;
+        for _ in signals.forever() {
+            num_signals += 1;
+            if num_signals == 1 {
+                println!(
+                    "Stopping, games being played have {}s to finish. Interrupt again to abort them",
+                    GRACE_PERIOD.as_secs()
+                );
+                let _ = nsend.send(Event::Command(Command::Stop));
+
+                let nsend = nsend.clone();
+                thread::spawn(move || {
+                    thread::sleep(GRACE_PERIOD);
+                    let _ = nsend.send(Event::Command(Command::Abort));
+                });
+            } else {
+                let _ = nsend.send(Event::Command(Command::Abort));
+            }
+        }
+    });
+
+    Ok(())
+}
+
+pub fn run(
+    settings: &TournamentSettings,
+    extra_observers: Vec<Box<dyn Observer>>,
+) -> Result<Results, String> {
     let (send, recv) = channel();
+    if settings.interactive {
+        let has_console = !settings.players.iter().any(|player| player.is_human());
+        spawn_input(&send, has_console)?;
+    }
+    run_with(settings, None, extra_observers, send, recv)
+}
+
+// Play a tournament taking commands from the given channel, with its output labelled if given
+pub(super) fn run_with(
+    settings: &TournamentSettings,
+    label: Option<&str>,
+    extra_observers: Vec<Box<dyn Observer>>,
+    send: Sender<Event>,
+    recv: Receiver<Event>,
+) -> Result<Results, String> {
     let mut threads = vec![];
     let mut player_stats: HashMap<usize, PlayerStatistics> = HashMap::new();
-    let should_stop = Arc::new(AtomicBool::new(false));
+    let controls = Arc::new(Controls::default());
     let mut pair_store: HashMap<usize, GameOutcome> = HashMap::new();
     let mut threads_running = 0;
+    let start = Instant::now();
+    if let Some(dir) = &settings.log {
+        create_dir_all(dir)
+            .map_err(|e| format!("Failed to create log directory {}: {}", dir, e))?;
+    }
+    let listener = match &settings.listen {
+        Some(address) => Some(
+            TcpListener::bind(listen_address(address))
+                .map_err(|e| format!("Failed to listen for workers on {}: {}", address, e))?,
+        ),
+        None => None,
+    };
+    let mut observers = builtin_observers(settings, label)?;
+    observers.extend(extra_observers);
 
     // Initialise player statistics
     for idx in 0..settings.players.len() {
@@ -39,138 +202,185 @@ pub fn run(settings: &TournamentSettings) {
         );
     }
 
-    // Keypress thread
-    let nsend = send.clone();
-    thread::spawn(move || {
-        loop {
-            let mut buffer = String::new();
-            let _ = std::io::stdin().read_line(&mut buffer);
-            let _ = nsend.send(Event::KeyPress);
-        }
-    });
+    // Statistics
+    let mut tournament_stats = TournamentStatistics::default();
 
     // Work generator
-    let generator = Arc::new(Mutex::new(Generator::new(
+    let mut generator = Generator::new(
         settings.players.len(),
         settings.fens.len(),
         settings.num_games,
-    )));
+    );
+
+    // Carry on from a previous run
+    if settings.resume
+        && let Some(path) = &settings.state
+    {
+        match state::load(path, settings) {
+            Ok(state) => {
+                generator.id = state.next_id;
+                generator.requeue = state.pending.into_iter().rev().collect();
+                tournament_stats = state.tournament_stats;
+                player_stats = state.player_stats;
+                pair_store = state.pair_store;
+                let msg = format!(
+                    "Resuming from {} with {} games completed",
+                    path, tournament_stats.games_completed
+                );
+                println!("{}", labelled(label, &msg));
+            }
+            Err(StateError::SettingsChanged) => {
+                return Err(format!(
+                    "Players or openings have changed since {} was saved, refusing to resume",
+                    path
+                ));
+            }
+            Err(err) => return Err(format!("Failed to load state {}: {}", path, err)),
+        }
+    }
+
+    let generator = Arc::new(Mutex::new(generator));
+
+    // Parameters being tuned, shared with the workers that apply them
+    let tuner = settings.tune.as_ref().map(|tune| {
+        Arc::new(Mutex::new(Tuner::new(
+            &tune.params,
+            settings.num_games.unwrap_or_default(),
+        )))
+    });
+
+    let _ = send.send(Event::TournamentStart);
 
     // Worker threads
-    for thread_id in 0..settings.num_threads {
+    let spawn_worker = |thread_id: usize| {
         let set = settings.clone();
         let nsend = send.clone();
-        let asd = should_stop.clone();
+        let asd = controls.clone();
         let dsa = generator.clone();
-        let thread = thread::spawn(move || worker(dsa, thread_id, nsend, &set, asd));
-        threads.push(thread);
+        let tuner = tuner.clone();
+        thread::spawn(move || worker(dsa, thread_id, nsend, &set, asd, tuner))
+    };
+    controls
+        .num_threads
+        .store(settings.num_threads, Ordering::Relaxed);
+    for thread_id in 0..settings.num_threads {
+        threads.push(spawn_worker(thread_id));
     }
 
-    // Statistics
-    let mut tournament_stats = TournamentStatistics::default();
+    // Remote workers
+    let listener = listener.map(|listener| {
+        let set = settings.clone();
+        let nsend = send.clone();
+        let asd = controls.clone();
+        let dsa = generator.clone();
+        let tuner = tuner.clone();
+        thread::spawn(move || listen(listener, dsa, nsend, &set, asd, tuner))
+    });
 
-    loop {
-        match recv.recv() {
+    while let Ok(event) = recv.recv() {
+        // Outputs
+        let update = matches!(event, Event::PrintUpdate)
+            .then(|| results::collect(&tournament_stats, &player_stats, settings, start.elapsed()));
+        for observer in observers.iter_mut() {
+            notify(observer.as_mut(), &event, update.as_ref());
+        }
+
+        match event {
             // Game
-            Ok(Event::GameStart(id, fen_idx)) => {
-                on_game_start(id, &settings.fens[fen_idx], settings.verbose)
+            Event::GameStart(..) => {}
+            Event::GameFinish(data) => {
+                generator.lock().expect("Lock fail thingy").finish(data.id);
+                if let Some(tuner) = &tuner {
+                    tuner.lock().expect("Lock fail thingy").finish_game(&data);
+                }
+                on_game_finish(
+                    send.clone(),
+                    &data,
+                    &mut pair_store,
+                    &mut player_stats,
+                    &mut tournament_stats,
+                    settings,
+                )
             }
-            Ok(Event::GameFinish(data)) => on_game_finish(
-                send.clone(),
-                &data,
-                &mut pair_store,
-                &mut player_stats,
-                &mut tournament_stats,
-                settings,
-            ),
-            Ok(Event::GamePairResult(p1, p2, r1, r2)) => {
-                on_game_pair_result(settings, &mut player_stats, p1, p2, r1, r2)
+            Event::GamePairResult(p1, p2, r1, r2) => {
+                on_game_pair_result(&mut player_stats, p1, p2, r1, r2)
             }
             // Tournament
-            Ok(Event::TournamentStart) => {}
-            Ok(Event::TournamentFinish) => should_stop.store(true, Ordering::Relaxed),
+            Event::TournamentStart => {}
+            Event::TournamentFinish => controls.should_stop.store(true, Ordering::Relaxed),
             // Players
-            Ok(Event::PlayerCreate(id)) => {
-                on_player_create(id, &mut tournament_stats, settings.verbose)
-            }
-            Ok(Event::PlayerDestroy) => on_player_destroy(&mut tournament_stats, settings.verbose),
+            Event::PlayerCreate(_) => on_player_create(&mut tournament_stats),
+            Event::PlayerDestroy(_) => on_player_destroy(&mut tournament_stats),
             // Threads
-            Ok(Event::ThreadStart(id)) => {
-                threads_running += 1;
-                on_thread_start(id, settings.verbose);
-            }
-            Ok(Event::ThreadFinish(id)) => {
+            Event::ThreadStart(_) => threads_running += 1,
+            Event::ThreadFinish(_) => {
                 threads_running -= 1;
-                on_thread_finish(id, settings.verbose);
-
                 if threads_running == 0 {
                     break;
                 }
             }
             // Results
-            Ok(Event::PrintUpdate) => on_print_results(&tournament_stats, &player_stats, settings),
+            Event::PrintUpdate => {
+                if let Some(tuner) = &tuner {
+                    save_tuner(settings, label, &tuner.lock().expect("Lock fail thingy"));
+                }
+                if let Some(path) = &settings.state {
+                    save_state(
+                        path,
+                        settings,
+                        &generator,
+                        &tournament_stats,
+                        &player_stats,
+                        &pair_store,
+                    );
+                }
+            }
             // Other
-            Ok(Event::KeyPress) => println!("keypress"),
-            Err(_e) => break,
+            Event::Command(command) => {
+                on_command(
+                    &command,
+                    &controls,
+                    &results::collect(&tournament_stats, &player_stats, settings, start.elapsed()),
+                    settings,
+                    label,
+                );
+
+                // Workers beyond the ones already running are created on demand
+                if let Command::Threads(n) = command {
+                    while threads.len() < n {
+                        threads.push(spawn_worker(threads.len()));
+                    }
+                }
+            }
         }
     }
 
     debug_assert_eq!(threads_running, 0);
     debug_assert!(recv.try_recv().is_err());
 
-    for thread in threads {
+    for thread in threads.into_iter().chain(listener) {
         let _ = thread.join();
     }
 
-    println!();
-    println!("Match Statistics:");
-    println!("Games played: {}", tournament_stats.games_completed);
-    println!("Players created: {}", tournament_stats.players_created);
-    println!("Players destroyed: {}", tournament_stats.players_destroyed);
-
-    if true {
-        println!();
-        println!("Debug:");
-        println!("Pair store: {}", pair_store.len());
-    }
-
-    println!();
-    println!(
-        "{:<3} {:<12} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4}",
-        "id", "name", "w", "l", "d", "ww", "wl", "wd", "lw", "ll", "ld", "dw", "dl", "dd"
-    );
-    for (id, stats) in &player_stats {
-        println!(
-            "{:<3} {:<12} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4} {:>4}",
-            id,
-            stats.name,
-            stats.wld.w,
-            stats.wld.l,
-            stats.wld.d,
-            stats.wld_pairs.ww,
-            stats.wld_pairs.wl,
-            stats.wld_pairs.wd,
-            stats.wld_pairs.lw,
-            stats.wld_pairs.ll,
-            stats.wld_pairs.ld,
-            stats.wld_pairs.dw,
-            stats.wld_pairs.dl,
-            stats.wld_pairs.dd
+    if let Some(path) = &settings.state {
+        save_state(
+            path,
+            settings,
+            &generator,
+            &tournament_stats,
+            &player_stats,
+            &pair_store,
         );
     }
 
-    println!();
-    println!("+2 +1 +0 -1 -2");
-    for stats in player_stats.values() {
-        let far_ahead = stats.wld_pairs.ww;
-        let ahead = stats.wld_pairs.wd + stats.wld_pairs.dw;
-        let even = stats.wld_pairs.wl + stats.wld_pairs.lw + stats.wld_pairs.dd;
-        let behind = stats.wld_pairs.ld + stats.wld_pairs.dl;
-        let far_behind = stats.wld_pairs.ll;
+    if let Some(tuner) = &tuner {
+        save_tuner(settings, label, &tuner.lock().expect("Lock fail thingy"));
+    }
 
-        println!(
-            "{:>2} {:>2} {:>2} {:>2} {:>2} {}",
-            far_ahead, ahead, even, behind, far_behind, stats.name
-        );
+    let results = results::collect(&tournament_stats, &player_stats, settings, start.elapsed());
+    for observer in observers.iter_mut() {
+        observer.on_end(&results);
     }
+
+    Ok(results)
 }
@@ -0,0 +1,289 @@
+use crate::tournament::{GameData, GameOutcome, TuneParameter};
+use std::{
+    collections::HashMap,
+    fs::{rename, write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// The usual SPSA constants, A is a fraction of the number of iterations
+const ALPHA: f32 = 0.602;
+const GAMMA: f32 = 0.101;
+const A_RATIO: f32 = 0.1;
+
+// name, start, min, max, c_end, r_end
+pub fn parse_spec(text: &str) -> Result<Vec<TuneParameter>, String> {
+    let mut params = vec![];
+
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+        let [name, start, min, max, c_end, r_end] = fields.as_slice() else {
+            return Err(format!(
+                "line {}: expected name, start, min, max, c_end, r_end",
+                idx + 1
+            ));
+        };
+
+        let number = |key: &str, value: &str| {
+            value.parse::<f32>().map_err(|_| {
+                format!(
+                    "line {}: {} expects a number, got \"{}\"",
+                    idx + 1,
+                    key,
+                    value
+                )
+            })
+        };
+
+        params.push(TuneParameter {
+            name: name.to_string(),
+            start: number("start", start)?,
+            min: number("min", min)?,
+            max: number("max", max)?,
+            c_end: number("c_end", c_end)?,
+            r_end: number("r_end", r_end)?,
+            is_integer: [start, min, max].iter().all(|value| !value.contains('.')),
+        });
+    }
+
+    Ok(params)
+}
+
+struct Pair {
+    flips: Vec<f32>,
+    c: Vec<f32>,
+    a: Vec<f32>,
+    plus: Vec<f32>,
+    minus: Vec<f32>,
+    score: f32,
+    games: usize,
+}
+
+pub struct Tuner {
+    params: Vec<TuneParameter>,
+    values: Vec<f32>,
+    iterations: usize,
+    completed: usize,
+    pairs: HashMap<usize, Pair>,
+    seed: u64,
+}
+
+impl Tuner {
+    #[must_use]
+    pub fn new(params: &[TuneParameter], num_games: usize) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default();
+
+        Self {
+            params: params.to_vec(),
+            values: params.iter().map(|param| param.start).collect(),
+            iterations: (num_games / 2).max(1),
+            completed: 0,
+            pairs: HashMap::new(),
+            seed: seed | 1,
+        }
+    }
+
+    // xorshift, good enough for coin flips
+    #[must_use]
+    fn flip(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        if self.seed & 1 == 0 { 1.0 } else { -1.0 }
+    }
+
+    fn create_pair(&mut self, pair_idx: usize) -> Pair {
+        let n = self.iterations as f32;
+        let k = (pair_idx + 1) as f32;
+        let big_a = A_RATIO * n;
+
+        let mut pair = Pair {
+            flips: vec![],
+            c: vec![],
+            a: vec![],
+            plus: vec![],
+            minus: vec![],
+            score: 0.0,
+            games: 0,
+        };
+
+        for idx in 0..self.params.len() {
+            let flip = self.flip();
+            let param = &self.params[idx];
+            let c = param.c_end * n.powf(GAMMA) / k.powf(GAMMA);
+            let a_end = param.r_end * param.c_end * param.c_end;
+            let a = a_end * (big_a + n).powf(ALPHA) / (big_a + k).powf(ALPHA);
+            let value = self.values[idx];
+
+            pair.plus
+                .push((value + c * flip).clamp(param.min, param.max));
+            pair.minus
+                .push((value - c * flip).clamp(param.min, param.max));
+            pair.flips.push(flip);
+            pair.c.push(c);
+            pair.a.push(a);
+        }
+
+        pair
+    }
+
+    #[must_use]
+    fn format(param: &TuneParameter, value: f32) -> String {
+        if param.is_integer {
+            format!("{}", value.round() as i64)
+        } else {
+            format!("{}", value)
+        }
+    }
+
+    // The options for a player in a game, the first player gets the positive perturbation
+    #[must_use]
+    pub fn options(&mut self, game_id: usize, player: usize) -> Vec<(String, String)> {
+        let pair_idx = game_id / 2;
+        if !self.pairs.contains_key(&pair_idx) {
+            let pair = self.create_pair(pair_idx);
+            self.pairs.insert(pair_idx, pair);
+        }
+        let pair = &self.pairs[&pair_idx];
+        let values = if player == 0 { &pair.plus } else { &pair.minus };
+
+        self.params
+            .iter()
+            .zip(values)
+            .map(|(param, value)| (param.name.clone(), Self::format(param, *value)))
+            .collect()
+    }
+
+    pub fn finish_game(&mut self, data: &GameData) {
+        let Some(pair) = self.pairs.get_mut(&(data.id / 2)) else {
+            return;
+        };
+
+        // Scored from the point of view of the first player
+        pair.score += match (data.outcome, data.player1 == 0) {
+            (Some(GameOutcome::P1win), true) | (Some(GameOutcome::P2win), false) => 1.0,
+            (Some(GameOutcome::P2win), true) | (Some(GameOutcome::P1win), false) => -1.0,
+            _ => 0.0,
+        };
+        pair.games += 1;
+
+        if pair.games < 2 {
+            return;
+        }
+
+        let pair = self.pairs.remove(&(data.id / 2)).unwrap();
+        for (idx, param) in self.params.iter().enumerate() {
+            let step = pair.a[idx] * pair.score / (pair.c[idx] * pair.flips[idx]);
+            self.values[idx] = (self.values[idx] + step).clamp(param.min, param.max);
+        }
+        self.completed += 1;
+    }
+
+    #[must_use]
+    pub fn summary(&self) -> String {
+        self.params
+            .iter()
+            .zip(&self.values)
+            .map(|(param, value)| format!("{}={}", param.name, Self::format(param, *value)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Written in the same format as the spec, so it can be used to carry on tuning
+    pub fn write(&self, path: &str) {
+        let mut text = format!("# {} of {} pairs\n", self.completed, self.iterations);
+        for (param, value) in self.params.iter().zip(&self.values) {
+            text += &format!(
+                "{}, {}, {}, {}, {}, {}\n",
+                param.name,
+                Self::format(param, *value),
+                param.min,
+                param.max,
+                param.c_end,
+                param.r_end
+            );
+        }
+
+        let tmp = format!("{}.tmp", path);
+        if let Err(err) = write(&tmp, text).and_then(|_| rename(&tmp, path)) {
+            println!("<Error> Failed to write tuning values {}: {}", path, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(id: usize, outcome: GameOutcome) -> GameData {
+        GameData {
+            id,
+            outcome: Some(outcome),
+            player1: id % 2,
+            player2: 1 - id % 2,
+            ply: 0,
+            fen: "startpos".to_string(),
+            moves: vec![],
+            timings: vec![],
+            aborted: None,
+        }
+    }
+
+    #[test]
+    fn spec() {
+        let params =
+            parse_spec("# comment\nHash, 16, 1, 64, 4, 0.002\n\nLMR, 0.5, 0.0, 1.0, 0.05, 0.002\n")
+                .unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].name, "Hash");
+        assert_eq!(params[0].start, 16.0);
+        assert!(params[0].is_integer);
+        assert!(!params[1].is_integer);
+
+        assert!(parse_spec("Hash, 16, 1, 64, 4").is_err());
+        assert!(parse_spec("Hash, 16, 1, x, 4, 0.002").is_err());
+    }
+
+    #[test]
+    fn tuning() {
+        let params = parse_spec("A, 50, 0, 100, 5, 0.002\nB, 50, 0, 100, 5, 0.002").unwrap();
+        let mut tuner = Tuner::new(&params, 100);
+
+        // Both games of a pair see the same perturbation, mirrored between the players
+        let plus = tuner.options(0, 0);
+        assert_eq!(tuner.options(1, 0), plus);
+        let minus = tuner.options(0, 1);
+        for ((_, p), (_, m)) in plus.iter().zip(&minus) {
+            let p = p.parse::<i64>().unwrap();
+            let m = m.parse::<i64>().unwrap();
+            assert_eq!(p + m, 100);
+            assert_ne!(p, m);
+        }
+
+        // The first player winning both games moves towards its values
+        let flips = tuner.pairs[&0].flips.clone();
+        tuner.finish_game(&game(0, GameOutcome::P1win));
+        tuner.finish_game(&game(1, GameOutcome::P2win));
+        assert!(tuner.pairs.is_empty());
+        assert_eq!(tuner.completed, 1);
+        for (value, flip) in tuner.values.iter().zip(flips) {
+            assert!((value - 50.0) * flip > 0.0);
+        }
+
+        // Values never leave their bounds
+        for id in 0..1000 {
+            let _ = tuner.options(id, 0);
+            tuner.finish_game(&game(id, GameOutcome::P1win));
+        }
+        for value in &tuner.values {
+            assert!((0.0..=100.0).contains(value));
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::tournament::{
+    PlayerStatistics, TournamentSettings, TournamentStatistics, play::GameOutcome,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{read_to_string, rename, write},
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct State {
+    pub hash: u64,
+    pub next_id: usize,
+    pub pending: Vec<usize>,
+    pub tournament_stats: TournamentStatistics,
+    pub player_stats: HashMap<usize, PlayerStatistics>,
+    pub pair_store: HashMap<usize, GameOutcome>,
+}
+
+#[derive(Debug)]
+pub enum StateError {
+    Read(String),
+    Write(String),
+    Parse(String),
+    SettingsChanged,
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::Read(err) => write!(f, "read failed: {}", err),
+            StateError::Write(err) => write!(f, "write failed: {}", err),
+            StateError::Parse(err) => write!(f, "parse failed: {}", err),
+            StateError::SettingsChanged => write!(f, "settings changed"),
+        }
+    }
+}
+
+// FNV-1a, so the hash stays the same between builds
+#[must_use]
+pub fn settings_hash(settings: &TournamentSettings) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut feed = |text: &str| {
+        for byte in text.bytes().chain([0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+
+    feed(&settings.game);
+    for player in &settings.players {
+        feed(&player.name);
+        feed(&player.path);
+        feed(&player.proto);
+        feed(&player.parameters);
+    }
+    for fen in &settings.fens {
+        feed(fen);
+    }
+
+    hash
+}
+
+pub fn save(path: &str, state: &State) -> Result<(), StateError> {
+    let json = serde_json::to_string(state).map_err(|e| StateError::Write(e.to_string()))?;
+
+    // Write to a temporary file first so a crash can't leave a half written state behind
+    let tmp = format!("{}.tmp", path);
+    write(&tmp, json).map_err(|e| StateError::Write(e.to_string()))?;
+    rename(&tmp, path).map_err(|e| StateError::Write(e.to_string()))
+}
+
+pub fn load(path: &str, settings: &TournamentSettings) -> Result<State, StateError> {
+    let json = read_to_string(path).map_err(|e| StateError::Read(e.to_string()))?;
+    let state: State = serde_json::from_str(&json).map_err(|e| StateError::Parse(e.to_string()))?;
+
+    if state.hash != settings_hash(settings) {
+        return Err(StateError::SettingsChanged);
+    }
+
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tournament::PlayerSettings;
+
+    fn get_settings() -> TournamentSettings {
+        TournamentSettings {
+            players: vec![
+                PlayerSettings {
+                    name: "Player 1".to_string(),
+                    path: "test1".to_string(),
+                    ..Default::default()
+                },
+                PlayerSettings {
+                    name: "Player 2".to_string(),
+                    path: "test2".to_string(),
+                    ..Default::default()
+                },
+            ],
+            fens: vec!["startpos".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn hash() {
+        let settings = get_settings();
+        assert_eq!(settings_hash(&settings), settings_hash(&settings.clone()));
+
+        // Things that don't change the games being played
+        let mut changed = settings.clone();
+        changed.num_threads = 8;
+        changed.verbose = true;
+        assert_eq!(settings_hash(&settings), settings_hash(&changed));
+
+        // Things that do
+        let mut changed = settings.clone();
+        changed.players[1].path = "test3".to_string();
+        assert_ne!(settings_hash(&settings), settings_hash(&changed));
+
+        let mut changed = settings.clone();
+        changed.fens.push("startpos".to_string());
+        assert_ne!(settings_hash(&settings), settings_hash(&changed));
+    }
+
+    #[test]
+    fn save_load() {
+        let settings = get_settings();
+        let path = std::env::temp_dir().join("cutergames_state_test.json");
+        let path = path.to_str().unwrap();
+
+        let mut player_stats = HashMap::new();
+        player_stats.insert(
+            0,
+            PlayerStatistics {
+                name: "Player 1".to_string(),
+                played: 3,
+                ..Default::default()
+            },
+        );
+
+        let state = State {
+            hash: settings_hash(&settings),
+            next_id: 4,
+            pending: vec![3],
+            tournament_stats: TournamentStatistics {
+                games_completed: 3,
+                ..Default::default()
+            },
+            player_stats,
+            pair_store: HashMap::from([(2, GameOutcome::Draw)]),
+        };
+        save(path, &state).unwrap();
+
+        let loaded = load(path, &settings).unwrap();
+        assert_eq!(loaded.next_id, 4);
+        assert_eq!(loaded.pending, vec![3]);
+        assert_eq!(loaded.tournament_stats.games_completed, 3);
+        assert_eq!(loaded.player_stats.get(&0).unwrap().played, 3);
+        assert!(loaded.pair_store.contains_key(&2));
+
+        let mut changed = settings.clone();
+        changed.fens.clear();
+        assert!(matches!(
+            load(path, &changed),
+            Err(StateError::SettingsChanged)
+        ));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
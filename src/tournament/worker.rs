@@ -1,71 +1,164 @@
 use crate::{
-    players::{self, Player},
-    tournament::{Event, TournamentSettings, generator::Generator, play::play},
+    players::{self, EngineCommand, Player, commlog},
+    tournament::{
+        Controls, Event, TournamentSettings,
+        affinity::worker_cores,
+        generator::{Generator, Work},
+        play::play,
+        spsa::Tuner,
+    },
 };
-use std::sync::{
-    Arc, Mutex,
-    atomic::{AtomicBool, Ordering},
-    mpsc::Sender,
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, atomic::Ordering, mpsc::Sender},
+    thread,
+    time::Duration,
 };
 
+// Create the players for a game and get them ready, or the id of a player that doesn't support the game
+pub(super) fn setup_players(
+    work: &Work,
+    options: &[Vec<(String, String)>; 2],
+    settings: &TournamentSettings,
+    cpus: &[usize],
+) -> Result<Vec<Box<dyn Player>>, usize> {
+    let ids = [work.player1, work.player2];
+    let mut players: Vec<Box<dyn Player>> = vec![];
+
+    for (slot, id) in ids.iter().enumerate() {
+        let player = settings.players.get(*id).unwrap();
+        let log_path = settings
+            .log
+            .as_ref()
+            .map(|dir| Path::new(dir).join(commlog::file_name(work.game_id, slot, &player.name)));
+        let command = EngineCommand {
+            path: player.path.clone(),
+            prefix: player.prefix.clone(),
+            cpus: cpus.to_vec(),
+        };
+        players.push(players::create(
+            &player.name,
+            &command,
+            players::get_protocol(&player.proto).unwrap(),
+            player.limits(),
+            &settings.game,
+            work.game_id,
+            player.debug,
+            log_path.as_deref(),
+        ));
+    }
+
+    for ((player, id), options) in players.iter_mut().zip(ids).zip(options) {
+        if !player.init() {
+            return Err(id);
+        }
+        for (name, value) in options {
+            player.set_option(name, value);
+        }
+        player.isready();
+    }
+
+    Ok(players)
+}
+
 pub fn worker(
     generator: Arc<Mutex<Generator>>,
     worker_id: usize,
     send: Sender<Event>,
     settings: &TournamentSettings,
-    should_stop: Arc<AtomicBool>,
+    controls: Arc<Controls>,
+    tuner: Option<Arc<Mutex<Tuner>>>,
 ) {
     let _ = send.send(Event::ThreadStart(worker_id));
 
-    while !should_stop.load(Ordering::Relaxed) {
-        let work = if let Some(work) = generator.lock().expect("Lock fail thingy").next() {
-            work
-        } else {
+    let cpus = settings.affinity.as_ref().map_or(vec![], |affinity| {
+        worker_cores(affinity, settings.num_threads, worker_id)
+    });
+
+    while !controls.should_stop.load(Ordering::Relaxed) {
+        // Wait while paused, or while there are more workers than wanted
+        if controls.is_paused.load(Ordering::Relaxed)
+            || worker_id >= controls.num_threads.load(Ordering::Relaxed)
+        {
+            if generator.lock().expect("Lock fail thingy").is_finished() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+            continue;
+        }
+
+        let (work, is_remote_pending) = {
+            let mut generator = generator.lock().expect("Lock fail thingy");
+            let work = generator.next();
+            (
+                work,
+                settings.listen.is_some() && !generator.in_progress.is_empty(),
+            )
+        };
+        let Some(work) = work else {
+            // Games being played remotely are handed back if their worker is lost
+            if is_remote_pending {
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
             break;
         };
 
-        // Create players
-        let mut players: Vec<Box<dyn Player>> = vec![
-            players::create(
-                &settings.players.get(work.player1).unwrap().name,
-                &settings.players.get(work.player1).unwrap().path,
-                players::get_protocol(&settings.players.get(work.player1).unwrap().proto).unwrap(),
-                settings.players.get(work.player1).unwrap().debug,
-            ),
-            players::create(
-                &settings.players.get(work.player2).unwrap().name,
-                &settings.players.get(work.player2).unwrap().path,
-                players::get_protocol(&settings.players.get(work.player2).unwrap().proto).unwrap(),
-                settings.players.get(work.player2).unwrap().debug,
-            ),
-        ];
+        let options = [work.player1, work.player2].map(|id| {
+            tuner.as_ref().map_or(vec![], |tuner| {
+                tuner
+                    .lock()
+                    .expect("Lock fail thingy")
+                    .options(work.game_id, id)
+            })
+        });
+
+        let mut players = match setup_players(&work, &options, settings, &cpus) {
+            Ok(players) => players,
+            // No point continuing the tournament
+            Err(id) => {
+                println!(
+                    "<Error> Player {} does not support game {}",
+                    settings.players.get(id).unwrap().name,
+                    settings.game
+                );
+                controls.should_stop.store(true, Ordering::Relaxed);
+                break;
+            }
+        };
 
         let _ = send.send(Event::PlayerCreate(work.player1));
         let _ = send.send(Event::PlayerCreate(work.player2));
 
-        // Initialise players
-        for player in players.iter_mut() {
-            player.init();
-            player.isready();
-        }
-
-        let _ = send.send(Event::GameStart(work.game_id, work.fen_idx));
+        let _ = send.send(Event::GameStart(
+            work.game_id,
+            work.fen_idx,
+            work.player1,
+            work.player2,
+            worker_id,
+        ));
         let result = play(
             work.game_id,
             work.player1,
             work.player2,
             &settings.fens[work.fen_idx],
             &mut players,
+            &settings.timing,
+            &controls.should_abort,
         );
 
-        if let Some(data) = result {
-            let _ = send.send(Event::GameFinish(data));
-        } else {
-            println!("Uh oh");
+        match result {
+            // Aborted games are left unfinished so they can be played again with --resume
+            _ if controls.should_abort.load(Ordering::Relaxed) => {}
+            Some(data) => {
+                let _ = send.send(Event::GameFinish(data));
+            }
+            None => println!("Uh oh"),
         }
 
-        for _ in players {
-            let _ = send.send(Event::PlayerDestroy);
+        drop(players);
+        for id in [work.player1, work.player2] {
+            let _ = send.send(Event::PlayerDestroy(id));
         }
     }
 
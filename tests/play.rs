@@ -0,0 +1,233 @@
+use cutergames::{
+    GameData, Observer, PlayerSettings, TournamentSettings, run,
+    tournament::{AbortReason, GameOutcome},
+};
+use std::{
+    fs::{remove_file, write},
+    sync::{Arc, Mutex},
+};
+
+// What's left of a finished game
+struct Game {
+    outcome: Option<GameOutcome>,
+    ply: usize,
+    aborted: Option<AbortReason>,
+}
+
+struct Collector(Arc<Mutex<Vec<Game>>>);
+
+impl Observer for Collector {
+    fn on_game_finish(&mut self, data: &GameData) {
+        self.0.lock().expect("Lock fail thingy").push(Game {
+            outcome: data.outcome,
+            ply: data.ply,
+            aborted: data.aborted.clone(),
+        });
+    }
+}
+
+// Settings for a single game between two fake engines following the scripts given, which are written to
+// temporary files until the game is played
+fn settings(
+    test: &str,
+    proto: &str,
+    movetime: Option<usize>,
+    scripts: [&str; 2],
+) -> TournamentSettings {
+    let players = scripts
+        .iter()
+        .enumerate()
+        .map(|(idx, script)| {
+            let name = format!(
+                "cutergames_play_{}_{}_{}.txt",
+                std::process::id(),
+                test,
+                idx
+            );
+            let path = std::env::temp_dir().join(name);
+            write(&path, script).unwrap();
+            PlayerSettings {
+                name: format!("P{}", idx + 1),
+                path: path.to_str().unwrap().to_string(),
+                proto: proto.to_string(),
+                prefix: env!("CARGO_BIN_EXE_fake_engine").to_string(),
+                movetime,
+                ..Default::default()
+            }
+        })
+        .collect();
+    TournamentSettings {
+        players,
+        fens: vec!["startpos".to_string()],
+        num_games: Some(1),
+        ..Default::default()
+    }
+}
+
+fn play_with(settings: &TournamentSettings) -> Game {
+    let games = Arc::new(Mutex::new(vec![]));
+    let results = run(settings, vec![Box::new(Collector(games.clone()))]);
+    for player in &settings.players {
+        let _ = remove_file(&player.path);
+    }
+    results.unwrap();
+    let mut games = games.lock().expect("Lock fail thingy");
+    assert_eq!(games.len(), 1);
+    games.pop().unwrap()
+}
+
+fn play(test: &str, proto: &str, movetime: usize, scripts: [&str; 2]) -> Game {
+    play_with(&settings(test, proto, Some(movetime), scripts))
+}
+
+#[test]
+fn success() {
+    let script = "gameover 6\nresult p1win\n";
+    let game = play("success", "ugi", 1000, [script, script]);
+    assert!(matches!(game.outcome, Some(GameOutcome::P1win)));
+    assert_eq!(game.ply, 6);
+    assert!(game.aborted.is_none());
+}
+
+#[test]
+fn noise() {
+    let script = "gameover 4\nresult p2win\nnoise\n";
+    let game = play("noise", "ugi", 1000, [script, script]);
+    assert!(matches!(game.outcome, Some(GameOutcome::P2win)));
+    assert_eq!(game.ply, 4);
+    assert!(game.aborted.is_none());
+}
+
+#[test]
+fn crash() {
+    let game = play("crash", "ugi", 1000, ["", "crash 2"]);
+    assert!(matches!(game.outcome, Some(GameOutcome::P1win)));
+    assert_eq!(game.ply, 3);
+    match game.aborted {
+        Some(AbortReason::Crash(1, stderr)) => {
+            assert_eq!(stderr, vec!["Crashing at move 2".to_string()])
+        }
+        aborted => panic!("{:?}", aborted),
+    }
+}
+
+#[test]
+fn timeout() {
+    let game = play("timeout", "ugi", 100, ["sleep 2 300", ""]);
+    assert!(matches!(game.outcome, Some(GameOutcome::P2win)));
+    assert_eq!(game.ply, 2);
+    assert!(matches!(game.aborted, Some(AbortReason::Timeout(0))));
+}
+
+#[test]
+fn slow_ready() {
+    // Time spent before readyok doesn't make up for time spent after go
+    let game = play("ready", "ugi", 100, ["readydelay 300\nsleep 2 300", ""]);
+    assert!(matches!(game.outcome, Some(GameOutcome::P2win)));
+    assert_eq!(game.ply, 2);
+    assert!(matches!(game.aborted, Some(AbortReason::Timeout(0))));
+}
+
+#[test]
+fn illegal_move() {
+    // The UAI judge knows the rules, d4 is out of reach of both of o's pieces
+    let game = play("illegal", "uai", 1000, ["moves f2", "moves d4"]);
+    assert!(matches!(game.outcome, Some(GameOutcome::P1win)));
+    assert_eq!(game.ply, 1);
+    match game.aborted {
+        Some(AbortReason::IllegalMove(1, mv)) => assert_eq!(mv, "d4"),
+        aborted => panic!("{:?}", aborted),
+    }
+}
+
+#[test]
+fn max_game_length() {
+    let game = play("length", "ugi", 1000, ["", ""]);
+    assert!(game.outcome.is_none());
+    assert_eq!(game.ply, 1024);
+    assert!(matches!(game.aborted, Some(AbortReason::MaxGameLength)));
+}
+
+#[test]
+fn no_turn() {
+    let game = play("turn", "ugi", 1000, ["noturn", ""]);
+    assert!(game.outcome.is_none());
+    assert_eq!(game.ply, 0);
+    assert!(matches!(game.aborted, Some(AbortReason::NoTurn)));
+}
+
+#[test]
+fn stall() {
+    let game = play("stall", "ugi", 100, ["", "stall 1"]);
+    assert!(matches!(game.outcome, Some(GameOutcome::P1win)));
+    assert_eq!(game.ply, 1);
+    assert!(matches!(game.aborted, Some(AbortReason::PlayerStall)));
+}
+
+#[test]
+fn untimed_stall() {
+    // Searching to a depth has no time to go by, so the stall time is all there is
+    let mut settings = settings("untimed", "ugi", None, ["", "stall 1"]);
+    for player in settings.players.iter_mut() {
+        player.depth = Some(1);
+    }
+    settings.timing.stall_time = 300;
+    let game = play_with(&settings);
+    assert!(matches!(game.outcome, Some(GameOutcome::P1win)));
+    assert_eq!(game.ply, 1);
+    assert!(matches!(game.aborted, Some(AbortReason::PlayerStall)));
+}
+
+fn play_pondering(test: &str, scripts: [&str; 2]) -> Game {
+    let mut settings = settings(test, "ugi", Some(1000), scripts);
+    for player in settings.players.iter_mut() {
+        player.ponder = true;
+    }
+    play_with(&settings)
+}
+
+// The fake engines exit if they're queried while pondering, and count the plies they're sent, so
+// a position left out of step ends the game at the wrong ply
+
+#[test]
+fn ponder_hit() {
+    let game = play_pondering(
+        "ponderhit",
+        [
+            "moves a1\nponder b1\ngameover 4\nresult p1win",
+            "moves b1\nponder a1\ngameover 4",
+        ],
+    );
+    assert!(matches!(game.outcome, Some(GameOutcome::P1win)));
+    assert_eq!(game.ply, 4);
+    assert!(game.aborted.is_none());
+}
+
+#[test]
+fn ponder_miss() {
+    let game = play_pondering(
+        "pondermiss",
+        [
+            "moves a1\nponder c1\ngameover 4\nresult p1win",
+            "moves b1\nponder c1\ngameover 4",
+        ],
+    );
+    assert!(matches!(game.outcome, Some(GameOutcome::P1win)));
+    assert_eq!(game.ply, 4);
+    assert!(game.aborted.is_none());
+}
+
+#[test]
+fn ponder_gameover() {
+    // The game ends on the first player's move with the second still pondering
+    let game = play_pondering(
+        "pondergameover",
+        [
+            "moves a1\nponder b1\ngameover 3\nresult p2win",
+            "moves b1\nponder a1\ngameover 3",
+        ],
+    );
+    assert!(matches!(game.outcome, Some(GameOutcome::P2win)));
+    assert_eq!(game.ply, 3);
+    assert!(game.aborted.is_none());
+}